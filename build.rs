@@ -0,0 +1,24 @@
+use std::process::Command;
+
+/// Makes the commit the bot was built from available as `GIT_COMMIT`, see `/woss version`.
+/// Heroku builds don't include the git directory, but provide the commit in
+/// `SOURCE_VERSION` instead.
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .or_else(|| {
+            std::env::var("SOURCE_VERSION")
+                .ok()
+                .map(|commit| commit.chars().take(7).collect())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=GIT_COMMIT={commit}");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-env-changed=SOURCE_VERSION");
+}
@@ -0,0 +1,39 @@
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+const COUNTRIES: &str = include_str!("../slack-ui/countries.json");
+lazy_static! {
+    static ref COUNTRY_LIST: Vec<Country> = serde_json::from_str(COUNTRIES).unwrap();
+}
+
+/// A country that contributors can select as their office. The options of the modal
+/// are built from this list, and submitted values are validated against it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Country {
+    /// Shown to the user
+    pub label: String,
+    /// Stored in the entries
+    pub value: String,
+}
+
+/// Makes sure that the country list can be parsed and isn't empty, so that a broken
+/// list is caught on startup rather than when a user opens the modal.
+pub fn validate() -> anyhow::Result<()> {
+    let countries: Vec<Country> = serde_json::from_str(COUNTRIES)?;
+    if countries.is_empty() {
+        anyhow::bail!("The country list is empty");
+    }
+    Ok(())
+}
+
+pub fn all() -> &'static [Country] {
+    &COUNTRY_LIST
+}
+
+/// Looks up a country by its label or value, ignoring case
+pub fn find(input: &str) -> Option<&'static Country> {
+    let input = input.trim();
+    all().iter().find(|country| {
+        country.label.eq_ignore_ascii_case(input) || country.value.eq_ignore_ascii_case(input)
+    })
+}
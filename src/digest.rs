@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use chrono::{Datelike, NaiveTime, Utc, Weekday};
+use tracing::{debug, error, info};
+
+use crate::{slack, AppConfig, AppState};
+
+/// When and where the weekly digest is posted. Times are in UTC.
+#[derive(Clone, Debug)]
+pub struct DigestSchedule {
+    pub weekday: Weekday,
+    pub time: NaiveTime,
+    pub channel_id: String,
+    pub mode: DigestMode,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DigestMode {
+    /// Posts a new message every week
+    Post,
+    /// Keeps a single pinned message up to date
+    Scoreboard,
+}
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Long enough to post the digest, but short enough that another instance can take over
+/// soon if the one holding the lock crashed
+const LOCK_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Periodically checks whether the weekly digest is due, and posts it if so. The week
+/// of the last digest is stored in redis, so restarts don't lead to double posting.
+/// If several instances of the bot are running, a lock in redis makes sure that only
+/// one of them posts the digest.
+pub async fn run(state: AppState, config: AppConfig, schedule: DigestSchedule) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now();
+        if now.weekday() != schedule.weekday || now.time() < schedule.time {
+            continue;
+        }
+
+        let week = format!("{}-W{:02}", now.iso_week().year(), now.iso_week().week());
+        if state.persistence.get_digest_last_run().await.as_deref() == Some(week.as_str()) {
+            continue;
+        }
+
+        let lock = format!("digest:{week}");
+        let token = match state.persistence.acquire_lock(&lock, LOCK_TTL).await {
+            Ok(Some(token)) => token,
+            Ok(None) => {
+                debug!("Another instance is posting the weekly digest for {week}");
+                continue;
+            }
+            Err(err) => {
+                error!("Failed to acquire the lock for the weekly digest: {err:?}");
+                continue;
+            }
+        };
+
+        post_digest(&state, &config, &schedule, week).await;
+
+        if let Err(err) = state.persistence.release_lock(&lock, &token).await {
+            error!("Failed to release the lock for the weekly digest: {err:?}");
+        }
+    }
+}
+
+/// Posts the digest of the given week, unless another instance posted it while this one
+/// was waiting for the lock
+async fn post_digest(
+    state: &AppState,
+    config: &AppConfig,
+    schedule: &DigestSchedule,
+    week: String,
+) {
+    if state.persistence.get_digest_last_run().await.as_deref() == Some(week.as_str()) {
+        return;
+    }
+
+    info!("Posting the weekly digest for {week}");
+    if let Err(err) =
+        slack::post_weekly_digest(state, config, &schedule.channel_id, schedule.mode).await
+    {
+        error!("Failed to post the weekly digest: {err:?}");
+        return;
+    }
+
+    if let Err(err) = state.persistence.set_digest_last_run(week).await {
+        error!("Failed to store the last run of the weekly digest: {err:?}");
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt::{Debug, Formatter};
+use std::time::Duration;
+
+use moka::future::Cache;
+
+use crate::models::OpenSourceAttachment;
+use crate::slack::EntryRange;
+
+/// Long enough to serve back-to-back stats requests, short enough that nobody wonders
+/// about stale numbers
+const TIME_TO_LIVE: Duration = Duration::from_secs(60);
+const MAX_CAPACITY: u64 = 100;
+
+/// Short-lived in-memory cache of the entries read by `slack::collect_entries`. All
+/// entries live in the same redis hash, so the cache is invalidated as a whole whenever
+/// an entry is stored.
+#[derive(Clone)]
+pub struct EntriesCache {
+    cache: Cache<EntryRange, Vec<OpenSourceAttachment>>,
+}
+
+impl EntriesCache {
+    pub fn new() -> Self {
+        EntriesCache {
+            cache: Cache::builder()
+                .time_to_live(TIME_TO_LIVE)
+                .max_capacity(MAX_CAPACITY)
+                .build(),
+        }
+    }
+
+    pub fn get(&self, range: &EntryRange) -> Option<Vec<OpenSourceAttachment>> {
+        self.cache.get(range)
+    }
+
+    pub async fn insert(&self, range: EntryRange, entries: Vec<OpenSourceAttachment>) {
+        self.cache.insert(range, entries).await;
+    }
+
+    pub fn invalidate(&self) {
+        self.cache.invalidate_all();
+    }
+}
+
+impl Debug for EntriesCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EntriesCache")
+            .field("entry_count", &self.cache.entry_count())
+            .finish()
+    }
+}
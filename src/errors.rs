@@ -1,18 +1,135 @@
+use std::fmt;
+
 use axum::response::IntoResponse;
 use axum::Json;
 use http::StatusCode;
 use redis::RedisError;
 use serde_json::json;
+use slack_morphism::errors::SlackClientError;
 use tracing::{debug, error};
 
+use crate::gateway;
+
+#[derive(Debug)]
 pub enum AppError {
     InternalServerError(anyhow::Error),
-    InputValidationError { field_name: String, message: String },
+    InputValidationError {
+        field_name: String,
+        message: String,
+    },
+    /// Several fields are invalid, as pairs of field name and message, so that all of
+    /// them can be shown at once
+    InputValidationErrors(Vec<(String, String)>),
+    /// The bot can't access a channel it needs, most likely the OSS channel, which can
+    /// only be fixed by an admin
+    ChannelMisconfigured(anyhow::Error),
+    /// A call to the Slack API failed, e.g. because Slack is down or rejected the request
+    SlackApi {
+        endpoint: &'static str,
+        source: SlackClientError,
+    },
+    /// A call to the Slack API took longer than `SLACK_REQUEST_TIMEOUT_SECS`
+    SlackTimeout {
+        endpoint: &'static str,
+    },
+}
+
+impl AppError {
+    /// Converts the error of a call to the given Slack API endpoint, e.g.
+    /// `.map_err(AppError::slack_api("chat.postMessage"))`. Errors that can only be fixed
+    /// by an admin become [`AppError::ChannelMisconfigured`], timeouts become
+    /// [`AppError::SlackTimeout`].
+    pub fn slack_api(endpoint: &'static str) -> impl FnOnce(SlackClientError) -> AppError {
+        move |source| {
+            if gateway::is_timeout_error(&source) {
+                AppError::SlackTimeout { endpoint }
+            } else if is_channel_access_error(&source) {
+                AppError::ChannelMisconfigured(
+                    anyhow::Error::new(source).context(format!("Slack API call {endpoint} failed")),
+                )
+            } else {
+                AppError::SlackApi { endpoint, source }
+            }
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::InternalServerError(inner) => write!(f, "{inner:#}"),
+            AppError::InputValidationError {
+                field_name,
+                message,
+            } => write!(f, "Invalid {field_name}: {message}"),
+            AppError::InputValidationErrors(errors) => {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|(field_name, message)| format!("{field_name}: {message}"))
+                    .collect();
+                write!(f, "Invalid input: {}", errors.join("; "))
+            }
+            AppError::ChannelMisconfigured(inner) => {
+                write!(f, "The bot can't access a channel: {inner:#}")
+            }
+            AppError::SlackApi { endpoint, source } => {
+                write!(f, "Slack API call {endpoint} failed: {source}")
+            }
+            AppError::SlackTimeout { endpoint } => {
+                write!(f, "Slack API call {endpoint} timed out")
+            }
+        }
+    }
+}
+
+// Allows passing the error through functions returning `anyhow::Result`, see the
+// conversion from `anyhow::Error` below
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::SlackApi { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Shown to users if the bot can't access the OSS channel
+pub const MISCONFIGURED_MESSAGE: &str =
+    "The bot isn't configured correctly, please contact an admin.";
+
+/// Shown to users if submitting the modal failed unexpectedly
+pub const INTERNAL_ERROR_MESSAGE: &str = "Something went wrong while recording your \
+    contribution, please try again later. If the problem persists, please contact an admin.";
+
+/// A modal that explains what went wrong. Slack only shows a generic error if a modal
+/// submission fails with an error status, so the modal is replaced with this instead.
+pub fn error_view(text: &str) -> serde_json::Value {
+    json!({
+        "type": "modal",
+        "title": { "type": "plain_text", "text": "Wizard of OSS" },
+        "blocks": [{
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": text },
+        }],
+    })
+}
+
+/// Slack error codes meaning that the channel doesn't exist or the bot isn't a member
+const CHANNEL_ACCESS_ERRORS: [&str; 2] = ["channel_not_found", "not_in_channel"];
+
+pub fn is_channel_access_error(err: &SlackClientError) -> bool {
+    match err {
+        SlackClientError::ApiError(err) => CHANNEL_ACCESS_ERRORS.contains(&err.code.as_str()),
+        _ => false,
+    }
 }
 
 impl From<anyhow::Error> for AppError {
     fn from(inner: anyhow::Error) -> Self {
-        AppError::InternalServerError(inner)
+        match inner.downcast::<AppError>() {
+            Ok(err) => err,
+            Err(inner) => AppError::InternalServerError(inner),
+        }
     }
 }
 
@@ -22,6 +139,41 @@ impl From<RedisError> for AppError {
     }
 }
 
+/// Returns a string field of the body of an API error response. E.g. Slack lists the
+/// scopes for `missing_scope` errors in the fields `needed` and `provided`.
+pub fn response_body_field(err: &SlackClientError, name: &str) -> Option<String> {
+    let SlackClientError::ApiError(err) = err else {
+        return None;
+    };
+
+    let body: serde_json::Value = serde_json::from_str(err.http_response_body.as_deref()?).ok()?;
+    body.get(name)?.as_str().map(String::from)
+}
+
+/// Logs the details Slack sent along with an error, such as the error code and missing
+/// scopes, which are hard to find in the `Debug` output of the error.
+pub fn log_slack_error(err: &SlackClientError) {
+    match err {
+        SlackClientError::ApiError(api_err) => {
+            error!(
+                "Slack API error '{}' (needed scopes: {}, provided scopes: {}, warnings: {:?})",
+                api_err.code,
+                response_body_field(err, "needed").unwrap_or_else(|| "-".to_string()),
+                response_body_field(err, "provided").unwrap_or_else(|| "-".to_string()),
+                api_err.warnings.clone().unwrap_or_default()
+            );
+        }
+        SlackClientError::HttpError(err) => {
+            error!(
+                "Slack HTTP error {}: {}",
+                err.status_code,
+                err.http_response_body.as_deref().unwrap_or("-")
+            );
+        }
+        err => error!("Slack client error: {err}"),
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
         use AppError::*;
@@ -38,6 +190,47 @@ impl IntoResponse for AppError {
                 )
             }
 
+            ChannelMisconfigured(inner) => {
+                error!(
+                    "The bot can't access a channel, make sure that SLACK_OSS_CHANNEL_ID is \
+                     correct and the bot was invited to the channel: {inner:?}"
+                );
+
+                // Replaces the modal if the error occurred while submitting it, and is
+                // shown as the response if it occurred while handling a command
+                (
+                    StatusCode::OK,
+                    json!({
+                        "text": MISCONFIGURED_MESSAGE,
+                        "response_action": "update",
+                        "view": error_view(MISCONFIGURED_MESSAGE),
+                    }),
+                )
+            }
+
+            SlackApi { endpoint, source } => {
+                error!("Slack API call {endpoint} failed, Slack may be unavailable");
+                log_slack_error(&source);
+
+                (
+                    StatusCode::BAD_GATEWAY,
+                    json!({
+                        "error": format!("Slack API call {endpoint} failed. See logs for details."),
+                    }),
+                )
+            }
+
+            SlackTimeout { endpoint } => {
+                error!("Slack API call {endpoint} timed out, Slack may be slow or unavailable");
+
+                (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    json!({
+                        "error": format!("Slack API call {endpoint} timed out."),
+                    }),
+                )
+            }
+
             InputValidationError {
                 field_name,
                 message,
@@ -54,6 +247,22 @@ impl IntoResponse for AppError {
                     }),
                 )
             }
+
+            InputValidationErrors(errors) => {
+                debug!("Rejecting submission because of validation errors: {errors:?}");
+
+                let errors: serde_json::Map<String, serde_json::Value> = errors
+                    .into_iter()
+                    .map(|(field_name, message)| (field_name, message.into()))
+                    .collect();
+                (
+                    StatusCode::OK,
+                    json!({
+                        "response_action": "errors",
+                        "errors": errors,
+                    }),
+                )
+            }
         };
 
         (status, Json(body)).into_response()
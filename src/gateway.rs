@@ -0,0 +1,694 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use slack_morphism::errors::{SlackClientError, SlackClientSystemError};
+use slack_morphism::prelude::*;
+use tokio::time::error::Elapsed;
+use tracing::{info, warn};
+
+/// The Slack API operations used by the bot. Handlers only talk to Slack through
+/// this trait, so that the actual API client can be swapped out, e.g. for testing.
+#[async_trait]
+pub trait SlackGateway: Debug + Send + Sync {
+    /// Returns a gateway that makes its calls using the given token, e.g. to act
+    /// on behalf of a different workspace
+    fn with_token(&self, api_token: SlackApiToken) -> Arc<dyn SlackGateway>;
+
+    async fn views_open(
+        &self,
+        req: &SlackApiViewsOpenRequest,
+    ) -> ClientResult<SlackApiViewsOpenResponse>;
+
+    async fn views_update(
+        &self,
+        req: &SlackApiViewsUpdateRequest,
+    ) -> ClientResult<SlackApiViewsUpdateResponse>;
+
+    async fn views_publish(
+        &self,
+        req: &SlackApiViewsPublishRequest,
+    ) -> ClientResult<SlackApiViewsPublishResponse>;
+
+    async fn chat_post_message(
+        &self,
+        req: &SlackApiChatPostMessageRequest,
+    ) -> ClientResult<SlackApiChatPostMessageResponse>;
+
+    async fn chat_post_ephemeral(
+        &self,
+        req: &SlackApiChatPostEphemeralRequest,
+    ) -> ClientResult<SlackApiChatPostEphemeralResponse>;
+
+    async fn chat_update(
+        &self,
+        req: &SlackApiChatUpdateRequest,
+    ) -> ClientResult<SlackApiChatUpdateResponse>;
+
+    async fn conversations_history(
+        &self,
+        req: &SlackApiConversationsHistoryRequest,
+    ) -> ClientResult<SlackApiConversationsHistoryResponse>;
+
+    async fn conversations_join(
+        &self,
+        req: &SlackApiConversationsJoinRequest,
+    ) -> ClientResult<SlackApiConversationsJoinResponse>;
+
+    async fn users_info(
+        &self,
+        req: &SlackApiUsersInfoRequest,
+    ) -> ClientResult<SlackApiUsersInfoResponse>;
+
+    async fn reactions_add(
+        &self,
+        req: &SlackApiReactionsAddRequest,
+    ) -> ClientResult<SlackApiReactionsAddResponse>;
+
+    async fn files_upload(
+        &self,
+        req: &SlackApiFilesUploadRequest,
+    ) -> ClientResult<SlackApiFilesUploadResponse>;
+
+    async fn pins_add(&self, req: &SlackApiPinsAddRequest)
+        -> ClientResult<SlackApiPinsAddResponse>;
+
+    async fn pins_remove(
+        &self,
+        req: &SlackApiPinsRemoveRequest,
+    ) -> ClientResult<SlackApiPinsRemoveResponse>;
+}
+
+/// [`SlackGateway`] implementation that calls the actual Slack API
+#[derive(Debug)]
+pub struct SlackApiGateway {
+    client: Arc<SlackHyperClient>,
+    api_token: SlackApiToken,
+    /// Calls that take longer fail with a timeout error, see [`timeout_error`]
+    request_timeout: Duration,
+}
+
+impl SlackApiGateway {
+    pub fn new(
+        client: Arc<SlackHyperClient>,
+        api_token: SlackApiToken,
+        request_timeout: Duration,
+    ) -> Self {
+        SlackApiGateway {
+            client,
+            api_token,
+            request_timeout,
+        }
+    }
+
+    // Sessions are lightweight and basically just a reference to client and token
+    fn session(&self) -> SlackClientSession<SlackClientHyperHttpsConnector> {
+        self.client.open_session(&self.api_token)
+    }
+}
+
+#[async_trait]
+impl SlackGateway for SlackApiGateway {
+    fn with_token(&self, api_token: SlackApiToken) -> Arc<dyn SlackGateway> {
+        Arc::new(SlackApiGateway::new(
+            self.client.clone(),
+            api_token,
+            self.request_timeout,
+        ))
+    }
+
+    async fn views_open(
+        &self,
+        req: &SlackApiViewsOpenRequest,
+    ) -> ClientResult<SlackApiViewsOpenResponse> {
+        let session = self.session();
+        call_slack_api("views.open", self.request_timeout, || {
+            session.views_open(req)
+        })
+        .await
+    }
+
+    async fn views_update(
+        &self,
+        req: &SlackApiViewsUpdateRequest,
+    ) -> ClientResult<SlackApiViewsUpdateResponse> {
+        let session = self.session();
+        call_slack_api("views.update", self.request_timeout, || {
+            session.views_update(req)
+        })
+        .await
+    }
+
+    async fn views_publish(
+        &self,
+        req: &SlackApiViewsPublishRequest,
+    ) -> ClientResult<SlackApiViewsPublishResponse> {
+        let session = self.session();
+        call_slack_api("views.publish", self.request_timeout, || {
+            session.views_publish(req)
+        })
+        .await
+    }
+
+    async fn chat_post_message(
+        &self,
+        req: &SlackApiChatPostMessageRequest,
+    ) -> ClientResult<SlackApiChatPostMessageResponse> {
+        let session = self.session();
+        call_slack_api("chat.postMessage", self.request_timeout, || {
+            session.chat_post_message(req)
+        })
+        .await
+    }
+
+    async fn chat_post_ephemeral(
+        &self,
+        req: &SlackApiChatPostEphemeralRequest,
+    ) -> ClientResult<SlackApiChatPostEphemeralResponse> {
+        let session = self.session();
+        call_slack_api("chat.postEphemeral", self.request_timeout, || {
+            session.chat_post_ephemeral(req)
+        })
+        .await
+    }
+
+    async fn chat_update(
+        &self,
+        req: &SlackApiChatUpdateRequest,
+    ) -> ClientResult<SlackApiChatUpdateResponse> {
+        let session = self.session();
+        call_slack_api("chat.update", self.request_timeout, || {
+            session.chat_update(req)
+        })
+        .await
+    }
+
+    async fn conversations_history(
+        &self,
+        req: &SlackApiConversationsHistoryRequest,
+    ) -> ClientResult<SlackApiConversationsHistoryResponse> {
+        let session = self.session();
+        call_slack_api("conversations.history", self.request_timeout, || {
+            session.conversations_history(req)
+        })
+        .await
+    }
+
+    async fn conversations_join(
+        &self,
+        req: &SlackApiConversationsJoinRequest,
+    ) -> ClientResult<SlackApiConversationsJoinResponse> {
+        let session = self.session();
+        call_slack_api("conversations.join", self.request_timeout, || {
+            session.conversations_join(req)
+        })
+        .await
+    }
+
+    async fn users_info(
+        &self,
+        req: &SlackApiUsersInfoRequest,
+    ) -> ClientResult<SlackApiUsersInfoResponse> {
+        let session = self.session();
+        call_slack_api("users.info", self.request_timeout, || {
+            session.users_info(req)
+        })
+        .await
+    }
+
+    async fn reactions_add(
+        &self,
+        req: &SlackApiReactionsAddRequest,
+    ) -> ClientResult<SlackApiReactionsAddResponse> {
+        let session = self.session();
+        call_slack_api("reactions.add", self.request_timeout, || {
+            session.reactions_add(req)
+        })
+        .await
+    }
+
+    async fn files_upload(
+        &self,
+        req: &SlackApiFilesUploadRequest,
+    ) -> ClientResult<SlackApiFilesUploadResponse> {
+        let session = self.session();
+        call_slack_api("files.upload", self.request_timeout, || {
+            session.files_upload(req)
+        })
+        .await
+    }
+
+    async fn pins_add(
+        &self,
+        req: &SlackApiPinsAddRequest,
+    ) -> ClientResult<SlackApiPinsAddResponse> {
+        let session = self.session();
+        call_slack_api("pins.add", self.request_timeout, || session.pins_add(req)).await
+    }
+
+    async fn pins_remove(
+        &self,
+        req: &SlackApiPinsRemoveRequest,
+    ) -> ClientResult<SlackApiPinsRemoveResponse> {
+        let session = self.session();
+        call_slack_api("pins.remove", self.request_timeout, || {
+            session.pins_remove(req)
+        })
+        .await
+    }
+}
+
+/// [`SlackGateway`] implementation for local development, which logs messages instead of
+/// posting them, so that contributors don't spam a real channel. Read-only calls and
+/// views are passed through to the wrapped gateway.
+#[derive(Debug)]
+pub struct DryRunGateway {
+    inner: Arc<dyn SlackGateway>,
+}
+
+impl DryRunGateway {
+    pub fn new(inner: Arc<dyn SlackGateway>) -> Self {
+        DryRunGateway { inner }
+    }
+}
+
+#[async_trait]
+impl SlackGateway for DryRunGateway {
+    fn with_token(&self, api_token: SlackApiToken) -> Arc<dyn SlackGateway> {
+        Arc::new(DryRunGateway::new(self.inner.with_token(api_token)))
+    }
+
+    async fn views_open(
+        &self,
+        req: &SlackApiViewsOpenRequest,
+    ) -> ClientResult<SlackApiViewsOpenResponse> {
+        self.inner.views_open(req).await
+    }
+
+    async fn views_update(
+        &self,
+        req: &SlackApiViewsUpdateRequest,
+    ) -> ClientResult<SlackApiViewsUpdateResponse> {
+        self.inner.views_update(req).await
+    }
+
+    async fn views_publish(
+        &self,
+        req: &SlackApiViewsPublishRequest,
+    ) -> ClientResult<SlackApiViewsPublishResponse> {
+        self.inner.views_publish(req).await
+    }
+
+    async fn chat_post_message(
+        &self,
+        req: &SlackApiChatPostMessageRequest,
+    ) -> ClientResult<SlackApiChatPostMessageResponse> {
+        log_dry_run("chat.postMessage", req);
+        dry_run_response(json!({
+            "channel": req.channel,
+            "ts": DRY_RUN_TS,
+            "message": { "ts": DRY_RUN_TS },
+        }))
+    }
+
+    async fn chat_post_ephemeral(
+        &self,
+        req: &SlackApiChatPostEphemeralRequest,
+    ) -> ClientResult<SlackApiChatPostEphemeralResponse> {
+        log_dry_run("chat.postEphemeral", req);
+        dry_run_response(json!({ "message_ts": DRY_RUN_TS }))
+    }
+
+    async fn chat_update(
+        &self,
+        req: &SlackApiChatUpdateRequest,
+    ) -> ClientResult<SlackApiChatUpdateResponse> {
+        log_dry_run("chat.update", req);
+        dry_run_response(json!({
+            "channel": req.channel,
+            "ts": req.ts,
+            "message": {},
+        }))
+    }
+
+    async fn conversations_history(
+        &self,
+        req: &SlackApiConversationsHistoryRequest,
+    ) -> ClientResult<SlackApiConversationsHistoryResponse> {
+        self.inner.conversations_history(req).await
+    }
+
+    // The bot has to be a member of the channel to read its history, which is passed
+    // through as well
+    async fn conversations_join(
+        &self,
+        req: &SlackApiConversationsJoinRequest,
+    ) -> ClientResult<SlackApiConversationsJoinResponse> {
+        self.inner.conversations_join(req).await
+    }
+
+    async fn users_info(
+        &self,
+        req: &SlackApiUsersInfoRequest,
+    ) -> ClientResult<SlackApiUsersInfoResponse> {
+        self.inner.users_info(req).await
+    }
+
+    async fn reactions_add(
+        &self,
+        req: &SlackApiReactionsAddRequest,
+    ) -> ClientResult<SlackApiReactionsAddResponse> {
+        log_dry_run("reactions.add", req);
+        dry_run_response(json!({}))
+    }
+
+    async fn files_upload(
+        &self,
+        req: &SlackApiFilesUploadRequest,
+    ) -> ClientResult<SlackApiFilesUploadResponse> {
+        log_dry_run("files.upload", req);
+        dry_run_response(json!({ "file": { "id": "F0000000000" } }))
+    }
+
+    async fn pins_add(
+        &self,
+        req: &SlackApiPinsAddRequest,
+    ) -> ClientResult<SlackApiPinsAddResponse> {
+        log_dry_run("pins.add", req);
+        dry_run_response(json!({}))
+    }
+
+    async fn pins_remove(
+        &self,
+        req: &SlackApiPinsRemoveRequest,
+    ) -> ClientResult<SlackApiPinsRemoveResponse> {
+        log_dry_run("pins.remove", req);
+        dry_run_response(json!({}))
+    }
+}
+
+/// The timestamp of messages that were only logged, see [`DryRunGateway`]
+const DRY_RUN_TS: &str = "0000000000.000000";
+
+fn log_dry_run(endpoint: &str, req: &impl Serialize) {
+    let payload = serde_json::to_string(req).unwrap_or_else(|err| format!("<{err}>"));
+    info!("Dry run, not calling Slack API {endpoint}: {payload}");
+}
+
+fn dry_run_response<T: DeserializeOwned>(response: serde_json::Value) -> ClientResult<T> {
+    serde_json::from_value(response).map_err(|err| {
+        SlackClientError::SystemError(
+            SlackClientSystemError::new().with_message(format!("Invalid dry run response: {err}")),
+        )
+    })
+}
+
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Returned if a Slack API call takes longer than the configured timeout. The cause is
+/// a [`tokio::time::error::Elapsed`], which is how [`is_timeout_error`] recognizes it.
+fn timeout_error(endpoint: &str, elapsed: Elapsed) -> SlackClientError {
+    SlackClientError::SystemError(
+        SlackClientSystemError::new()
+            .with_message(format!("Slack API call {endpoint} timed out"))
+            .with_cause(Box::new(elapsed)),
+    )
+}
+
+pub fn is_timeout_error(err: &SlackClientError) -> bool {
+    match err {
+        SlackClientError::SystemError(err) => err
+            .cause
+            .as_ref()
+            .map_or(false, |cause| cause.is::<Elapsed>()),
+        _ => false,
+    }
+}
+
+/// Calls the given Slack API operation, retrying it if Slack responds with a rate limit
+/// error (HTTP 429). The `Retry-After` duration is honored if Slack sent one, otherwise
+/// we fall back to an exponential backoff. After [`MAX_RATE_LIMIT_RETRIES`] retries the
+/// rate limit error is returned to the caller. Each attempt may take up to `timeout`.
+async fn call_slack_api<T, F, Fut>(
+    endpoint: &str,
+    timeout: Duration,
+    mut call: F,
+) -> ClientResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ClientResult<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        let result = tokio::time::timeout(timeout, call())
+            .await
+            .unwrap_or_else(|elapsed| Err(timeout_error(endpoint, elapsed)));
+
+        match result {
+            Err(SlackClientError::RateLimitError(err)) if attempt < MAX_RATE_LIMIT_RETRIES => {
+                let delay = err
+                    .retry_after
+                    .unwrap_or_else(|| Duration::from_secs(2u64.pow(attempt)));
+                attempt += 1;
+
+                warn!(
+                    "Slack API {endpoint} is rate limited, retrying in {delay:?} \
+                     (attempt {attempt}/{MAX_RATE_LIMIT_RETRIES})"
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::collections::{HashMap, HashSet, VecDeque};
+    use std::sync::Mutex;
+
+    use slack_morphism::errors::SlackClientApiError;
+
+    use super::*;
+
+    /// The timestamp of all messages posted through the [`RecordingGateway`]
+    pub const POSTED_TS: &str = "1700000000.000100";
+
+    /// [`SlackGateway`] implementation for tests, which records the calls made through it
+    /// and answers them with canned responses. Clones share their recorded calls, so
+    /// that calls made through [`SlackGateway::with_token`] are recorded as well.
+    #[derive(Debug, Default, Clone)]
+    pub struct RecordingGateway {
+        calls: Arc<Mutex<Vec<(&'static str, serde_json::Value)>>>,
+        /// Returned by users.info, by user ID. Unknown users are named after their ID.
+        usernames: HashMap<String, String>,
+        /// Returned by conversations.history, one page per call
+        history_pages: Arc<Mutex<VecDeque<serde_json::Value>>>,
+        /// Endpoints whose next call fails, see [`RecordingGateway::fail_next`]
+        failing: Arc<Mutex<HashSet<&'static str>>>,
+    }
+
+    impl RecordingGateway {
+        pub fn new() -> Self {
+            RecordingGateway::default()
+        }
+
+        pub fn with_username(mut self, user_id: &str, username: &str) -> Self {
+            self.usernames
+                .insert(user_id.to_string(), username.to_string());
+            self
+        }
+
+        /// Queues a page of the channel history, which must at least contain `messages`
+        pub fn with_history_page(self, page: serde_json::Value) -> Self {
+            self.history_pages.lock().unwrap().push_back(page);
+            self
+        }
+
+        /// Makes the next call to the given endpoint fail with a Slack API error. The call
+        /// is recorded nonetheless.
+        pub fn fail_next(&self, endpoint: &'static str) {
+            self.failing.lock().unwrap().insert(endpoint);
+        }
+
+        /// The endpoints and serialized requests of all calls, in the order they were made
+        pub fn calls(&self) -> Vec<(&'static str, serde_json::Value)> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        pub fn endpoints(&self) -> Vec<&'static str> {
+            self.calls()
+                .into_iter()
+                .map(|(endpoint, _)| endpoint)
+                .collect()
+        }
+
+        /// The requests made to the given endpoint
+        pub fn requests(&self, endpoint: &str) -> Vec<serde_json::Value> {
+            self.calls()
+                .into_iter()
+                .filter(|(called, _)| *called == endpoint)
+                .map(|(_, req)| req)
+                .collect()
+        }
+
+        /// Waits for a call to the given endpoint, e.g. from a task spawned by a handler,
+        /// and returns the latest request made to it
+        pub async fn wait_for(&self, endpoint: &str) -> serde_json::Value {
+            for _ in 0..100 {
+                if let Some(req) = self.requests(endpoint).pop() {
+                    return req;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("Slack API {endpoint} was not called");
+        }
+
+        fn record(&self, endpoint: &'static str, req: &impl Serialize) -> ClientResult<()> {
+            let req = serde_json::to_value(req).expect("Requests can be serialized");
+            self.calls.lock().unwrap().push((endpoint, req));
+
+            if self.failing.lock().unwrap().remove(endpoint) {
+                let err = SlackClientApiError::new("internal_error".to_string());
+                return Err(SlackClientError::ApiError(err));
+            }
+            Ok(())
+        }
+    }
+
+    /// Slack responds with the view it opened, plus its state
+    fn view_response<T: DeserializeOwned>(view: &SlackView) -> ClientResult<T> {
+        let mut view = serde_json::to_value(view).expect("Views can be serialized");
+        view["id"] = json!("V0000000000");
+        view["team_id"] = json!("T0000000000");
+        view["hash"] = json!("0");
+        dry_run_response(json!({ "view": view }))
+    }
+
+    #[async_trait]
+    impl SlackGateway for RecordingGateway {
+        fn with_token(&self, _api_token: SlackApiToken) -> Arc<dyn SlackGateway> {
+            Arc::new(self.clone())
+        }
+
+        async fn views_open(
+            &self,
+            req: &SlackApiViewsOpenRequest,
+        ) -> ClientResult<SlackApiViewsOpenResponse> {
+            self.record("views.open", req)?;
+            view_response(&req.view)
+        }
+
+        async fn views_update(
+            &self,
+            req: &SlackApiViewsUpdateRequest,
+        ) -> ClientResult<SlackApiViewsUpdateResponse> {
+            self.record("views.update", req)?;
+            view_response(&req.view)
+        }
+
+        async fn views_publish(
+            &self,
+            req: &SlackApiViewsPublishRequest,
+        ) -> ClientResult<SlackApiViewsPublishResponse> {
+            self.record("views.publish", req)?;
+            view_response(&req.view)
+        }
+
+        async fn chat_post_message(
+            &self,
+            req: &SlackApiChatPostMessageRequest,
+        ) -> ClientResult<SlackApiChatPostMessageResponse> {
+            self.record("chat.postMessage", req)?;
+            dry_run_response(json!({
+                "channel": req.channel,
+                "ts": POSTED_TS,
+                "message": { "ts": POSTED_TS },
+            }))
+        }
+
+        async fn chat_post_ephemeral(
+            &self,
+            req: &SlackApiChatPostEphemeralRequest,
+        ) -> ClientResult<SlackApiChatPostEphemeralResponse> {
+            self.record("chat.postEphemeral", req)?;
+            dry_run_response(json!({ "message_ts": POSTED_TS }))
+        }
+
+        async fn chat_update(
+            &self,
+            req: &SlackApiChatUpdateRequest,
+        ) -> ClientResult<SlackApiChatUpdateResponse> {
+            self.record("chat.update", req)?;
+            dry_run_response(json!({
+                "channel": req.channel,
+                "ts": req.ts,
+                "message": {},
+            }))
+        }
+
+        async fn conversations_history(
+            &self,
+            req: &SlackApiConversationsHistoryRequest,
+        ) -> ClientResult<SlackApiConversationsHistoryResponse> {
+            self.record("conversations.history", req)?;
+            let page = self.history_pages.lock().unwrap().pop_front();
+            dry_run_response(page.unwrap_or_else(|| json!({ "messages": [] })))
+        }
+
+        async fn conversations_join(
+            &self,
+            req: &SlackApiConversationsJoinRequest,
+        ) -> ClientResult<SlackApiConversationsJoinResponse> {
+            self.record("conversations.join", req)?;
+            dry_run_response(json!({ "channel": { "id": req.channel, "created": 0 } }))
+        }
+
+        async fn users_info(
+            &self,
+            req: &SlackApiUsersInfoRequest,
+        ) -> ClientResult<SlackApiUsersInfoResponse> {
+            self.record("users.info", req)?;
+            let user_id = &req.user.0;
+            let username = self.usernames.get(user_id).unwrap_or(user_id);
+            dry_run_response(json!({ "user": { "id": user_id, "name": username } }))
+        }
+
+        async fn reactions_add(
+            &self,
+            req: &SlackApiReactionsAddRequest,
+        ) -> ClientResult<SlackApiReactionsAddResponse> {
+            self.record("reactions.add", req)?;
+            dry_run_response(json!({}))
+        }
+
+        async fn files_upload(
+            &self,
+            req: &SlackApiFilesUploadRequest,
+        ) -> ClientResult<SlackApiFilesUploadResponse> {
+            self.record("files.upload", req)?;
+            dry_run_response(json!({ "file": { "id": "F0000000000" } }))
+        }
+
+        async fn pins_add(
+            &self,
+            req: &SlackApiPinsAddRequest,
+        ) -> ClientResult<SlackApiPinsAddResponse> {
+            self.record("pins.add", req)?;
+            dry_run_response(json!({}))
+        }
+
+        async fn pins_remove(
+            &self,
+            req: &SlackApiPinsRemoveRequest,
+        ) -> ClientResult<SlackApiPinsRemoveResponse> {
+            self.record("pins.remove", req)?;
+            dry_run_response(json!({}))
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use anyhow::Context;
+use lazy_static::lazy_static;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use rand::prelude::SliceRandom;
+use tracing::{error, info, warn};
+
+const DEFAULT_LOADING_MESSAGES: &str = include_str!("../loading-messages.txt");
+lazy_static! {
+    static ref LOADING_MESSAGES: RwLock<Vec<String>> = RwLock::new(parse(DEFAULT_LOADING_MESSAGES));
+}
+
+/// Returns a random loading message, or an empty string if there are none
+pub fn random() -> String {
+    let messages = LOADING_MESSAGES.read().unwrap();
+    messages
+        .choose(&mut rand::thread_rng())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Replaces the loading messages with the contents of the given file, one message
+/// per line. If the file can't be read, the current messages are kept.
+pub fn load(path: &Path) -> anyhow::Result<()> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Couldn't read loading messages from {}", path.display()))?;
+
+    let messages = parse(&contents);
+    if messages.is_empty() {
+        warn!(
+            "{} doesn't contain any loading messages, a fixed text is shown instead",
+            path.display()
+        );
+    }
+    info!(
+        "Loaded {} loading messages from {}",
+        messages.len(),
+        path.display()
+    );
+    *LOADING_MESSAGES.write().unwrap() = messages;
+
+    Ok(())
+}
+
+/// Reloads the loading messages whenever the given file changes. The watcher stops
+/// when the returned value is dropped.
+///
+/// The parent directory is watched rather than the file itself, because many editors
+/// replace the file when saving, which would otherwise end the watch.
+pub fn watch(path: &Path) -> anyhow::Result<RecommendedWatcher> {
+    let file: PathBuf = path.to_path_buf();
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let event = match res {
+            Ok(event) => event,
+            Err(err) => {
+                error!("Error while watching loading messages: {err}");
+                return;
+            }
+        };
+
+        if !event
+            .paths
+            .iter()
+            .any(|x| x.file_name() == file.file_name())
+        {
+            return;
+        }
+
+        if let Err(err) = load(&file) {
+            error!("Couldn't reload loading messages: {err:#}");
+        }
+    })?;
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
+
+/// One message per line. Blank lines are skipped, so that e.g. a trailing newline
+/// doesn't result in an empty message.
+fn parse(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
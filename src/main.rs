@@ -1,41 +1,79 @@
 extern crate core;
 
+mod countries;
+mod digest;
+mod entries_cache;
 mod errors;
+mod gateway;
+mod loading_messages;
+mod message_template;
 mod models;
 mod persistence;
 mod request_handlers;
 mod server;
 mod slack;
+mod store;
+mod webhook;
 
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Context;
-use lazy_static::lazy_static;
+use anyhow::{anyhow, Context};
+use chrono::{NaiveTime, Weekday};
 use slack_morphism::prelude::*;
-
-const RAW_LOADING_MESSAGES: &str = include_str!("../loading-messages.txt");
-lazy_static! {
-    static ref LOADING_MESSAGES: Vec<&'static str> = RAW_LOADING_MESSAGES.split('\n').collect();
-}
+use tracing_subscriber::EnvFilter;
 
 #[derive(Clone, Debug)]
 pub struct AppState {
-    client: Arc<SlackHyperClient>,
-    api_token: SlackApiToken,
+    pub slack: Arc<dyn gateway::SlackGateway>,
     pub persistence: persistence::Persistence,
+    pub entries_cache: entries_cache::EntriesCache,
+    /// When the server was started, see `/woss version`
+    pub started_at: Instant,
 }
 
 impl AppState {
-    // Sessions are lightweight and basically just a reference to client and token
-    pub fn get_session(&self) -> SlackClientSession<SlackClientHyperHttpsConnector> {
-        self.client.open_session(&self.api_token)
+    /// Returns a state whose Slack gateway acts on behalf of the given workspace, using
+    /// the token stored when the app was installed there. If the app wasn't installed
+    /// through OAuth, the default token is used.
+    pub async fn for_team(&self, team_id: &SlackTeamId) -> AppState {
+        match self.persistence.get_team_token(team_id).await {
+            Some(token) => AppState {
+                slack: self.slack.with_token(SlackApiToken::new(token.into())),
+                ..self.clone()
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// A state that keeps its data in memory and talks to Slack through the given gateway
+    #[cfg(test)]
+    pub fn for_tests(slack: Arc<dyn gateway::SlackGateway>) -> AppState {
+        AppState {
+            slack,
+            persistence: persistence::Persistence::in_memory(),
+            entries_cache: entries_cache::EntriesCache::new(),
+            started_at: Instant::now(),
+        }
     }
 }
 
+/// Used if `PORT` isn't set
+const DEFAULT_PORT: u16 = 8080;
+
 #[derive(Clone, Debug)]
 pub struct AppConfig {
     port: u16,
+    storage_backend: store::StorageBackend,
+    /// If enabled, the bot starts even if redis can't be reached, keeping its data in
+    /// memory until it is restarted, see `Persistence::new`
+    allow_degraded_start: bool,
+    /// Only required for the redis backend
     redis_url: String,
+    /// Prefix of all redis keys
+    redis_namespace: String,
     slack_client_id: String,
     slack_client_secret: String,
     slack_bot_scope: String,
@@ -43,25 +81,624 @@ pub struct AppConfig {
     slack_signing_secret: String,
     slack_test_token: String,
     slack_oss_channel_id: String,
+    slack_success_reaction: String,
+    /// How long connecting to the Slack API may take
+    slack_connect_timeout: Duration,
+    /// How long a single Slack API call may take, including connecting
+    slack_request_timeout: Duration,
+    /// Alternative words for subcommands, e.g. `log` for `record`
+    command_aliases: HashMap<String, String>,
+    /// Users that may run admin commands such as `/woss status`, and ignore entries
+    admin_user_ids: Vec<String>,
+    /// The team of each user ID, for `/woss stats teams`
+    user_teams: HashMap<String, String>,
+    /// If an admin reacts with this emoji to an entry, it is left out of the stats
+    ignore_reaction: String,
+    /// Reacting with this emoji to a message offers to log the link in it, if set
+    quick_log_reaction: Option<String>,
+    post_identity: PostIdentity,
+    /// Which of the contributor's profile images is used when posting as them
+    profile_image_size: ImageSize,
+    /// Shown above the fields of posted entries
+    attachment_title: Option<String>,
+    /// Shown alongside posted entries, e.g. "Logged via Wizard of OSS"
+    attachment_footer: Option<String>,
+    /// Shown next to the footer, only used if a footer is configured
+    attachment_footer_icon_url: Option<String>,
+    /// The text of posted entries, with placeholders such as `{user}`, see
+    /// `message_template::PLACEHOLDERS`. Entries only consist of the attachment if unset.
+    success_message: Option<String>,
+    /// Entries with at least this many hours are highlighted in blue
+    medium_effort_hours: f64,
+    /// Entries with at least this many hours are highlighted in gold
+    large_effort_hours: f64,
+    /// Entries with more hours are rejected, as they are most likely typos
+    max_hours_per_entry: f64,
+    /// If disabled, the description is optional
+    require_description: bool,
+    /// Shorter descriptions are rejected, unless descriptions aren't required
+    min_description_length: usize,
+    /// Longer descriptions are rejected when submitting the modal
+    max_description_length: usize,
+    /// Descriptions are shortened to this many characters in `/woss stats -v`
+    stats_description_length: usize,
+    /// Which entry counts for `/woss leaderboard unique` if a URL was logged several times
+    unique_url_credit: models::UniqueUrlCredit,
+    /// Optional behaviors that can be switched on and off
+    features: FeatureFlags,
+    /// How the hours of entries with co-authors are credited in the stats
+    co_author_hours: models::HoursAttribution,
+    /// How often a user may open the modal via `/woss` per minute, 0 disables the limit
+    record_rate_limit: u32,
+    /// How many pages of up to 200 messages are read from the channel history at most,
+    /// 0 disables the limit. The history is only read by `/woss backfill`, and for the
+    /// stats in degraded mode. Otherwise the stats read the stored entries of their range.
+    history_max_pages: u32,
+    /// If not empty, only URLs with one of these hosts or their subdomains are accepted
+    allowed_url_hosts: Vec<String>,
+    /// If enabled, URLs that respond with an error status are rejected. Off by default,
+    /// as it slows down submissions and rejects e.g. links to private repositories.
+    /// Note that this makes the bot request URLs chosen by users. URLs resolving to
+    /// loopback, private or link-local addresses are rejected before the request, but
+    /// a host whose DNS answer changes in between could still get through, so only
+    /// enable this where the bot can't reach sensitive internal services.
+    verify_url_reachable: bool,
+    /// If set, loading messages are read from this file instead of the embedded
+    /// default, and reloaded whenever it changes
+    loading_messages_path: Option<String>,
+    /// If disabled, a fixed text is shown while commands are processed
+    random_loading_messages: bool,
+    /// Shown before the random loading message, e.g. "Please wait..."
+    loading_message_prefix: String,
+    /// Name of an emoji shown before the loading message, without colons
+    loading_message_emoji: Option<String>,
+    /// New entries are POSTed to this URL as JSON, if set
+    webhook_url: Option<String>,
+    /// If set, webhook requests are signed with this secret, see `webhook::notify`
+    webhook_secret: Option<String>,
+    /// Required in the `X-Admin-Secret` header of requests to `/admin` routes, which are
+    /// disabled if unset
+    admin_api_secret: Option<String>,
+    log_format: LogFormat,
+    /// Logs messages instead of posting them to Slack, for local development
+    dry_run: bool,
+    /// The weekly digest is only posted if a schedule is configured
+    digest_schedule: Option<digest::DigestSchedule>,
+}
+
+/// Who entries are posted as
+#[derive(Clone, Debug)]
+pub enum PostIdentity {
+    /// The contributor's name and profile image, which requires `chat:write.customize`.
+    /// The suffix is appended to the name, e.g. " via Wizard of OSS".
+    Contributor { suffix: String },
+    /// The same identity for all entries. If no username or icon is configured, the
+    /// bot's own is used.
+    Single {
+        username: Option<String>,
+        icon_url: Option<String>,
+    },
+}
+
+/// Slack provides profile images in several sizes, see `slack::profile_image_url`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageSize {
+    Smallest,
+    Largest,
+    /// Width in pixels, e.g. 48. If Slack doesn't provide this size, the next larger
+    /// one is used, or the largest if there is none.
+    Exact(u32),
+}
+
+/// Optional behaviors, configured with `WOSS_FEATURES`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeatureFlags {
+    /// Posted entries get a reaction, see `SLACK_SUCCESS_REACTION`
+    reactions: bool,
+    /// Entries are posted as replies to a message per project
+    project_threads: bool,
+    /// `/woss stats trend` is available
+    trend: bool,
+}
+
+impl FeatureFlags {
+    /// The names used in `WOSS_FEATURES`
+    const NAMES: [&'static str; 3] = ["reactions", "project_threads", "trend"];
+
+    /// Used if `WOSS_FEATURES` isn't set
+    const DEFAULT: FeatureFlags = FeatureFlags {
+        reactions: true,
+        project_threads: false,
+        trend: true,
+    };
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LogFormat {
+    Text,
+    Json,
 }
 
 impl AppConfig {
+    /// Reads the configuration from the environment. All missing or invalid variables are
+    /// reported at once, so that they can be fixed in one go.
     fn from_env() -> Result<Self, anyhow::Error> {
-        Ok(AppConfig {
-            port: Self::env_var("PORT")?.parse()?,
-            redis_url: Self::env_var("REDISCLOUD_URL")?,
-            slack_client_id: Self::env_var("SLACK_CLIENT_ID")?,
-            slack_client_secret: Self::env_var("SLACK_CLIENT_SECRET")?,
-            slack_bot_scope: Self::env_var("SLACK_BOT_SCOPE")?,
-            slack_redirect_host: Self::env_var("SLACK_REDIRECT_HOST")?,
-            slack_signing_secret: Self::env_var("SLACK_SIGNING_SECRET")?,
-            slack_test_token: Self::env_var("SLACK_TEST_TOKEN")?,
-            slack_oss_channel_id: Self::env_var("SLACK_OSS_CHANNEL_ID")?,
-        })
+        let mut errors = ConfigErrors::default();
+        let storage_backend = errors.check(
+            Self::storage_backend_from_env(),
+            store::StorageBackend::Redis,
+        );
+
+        let config = AppConfig {
+            port: errors.check(Self::port_from_env(), DEFAULT_PORT),
+            storage_backend,
+            allow_degraded_start: errors.check(
+                Self::parsed_env_var_or("ALLOW_DEGRADED_START", false),
+                false,
+            ),
+            redis_url: match storage_backend {
+                store::StorageBackend::Redis => errors.env_var("REDISCLOUD_URL"),
+                store::StorageBackend::Memory => String::new(),
+            },
+            redis_namespace: Self::optional_env_var("REDIS_NAMESPACE")
+                .unwrap_or_else(|| "woss".to_string()),
+            slack_client_id: errors.env_var("SLACK_CLIENT_ID"),
+            slack_client_secret: errors.env_var("SLACK_CLIENT_SECRET"),
+            slack_bot_scope: errors.env_var("SLACK_BOT_SCOPE"),
+            slack_redirect_host: errors.env_var("SLACK_REDIRECT_HOST"),
+            slack_signing_secret: errors.secret_env_var("SLACK_SIGNING_SECRET"),
+            slack_test_token: errors.secret_env_var("SLACK_TEST_TOKEN"),
+            slack_oss_channel_id: errors.env_var("SLACK_OSS_CHANNEL_ID"),
+            slack_success_reaction: Self::optional_env_var("SLACK_SUCCESS_REACTION")
+                .unwrap_or_else(|| "tada".to_string()),
+            slack_connect_timeout: Duration::from_secs(errors.check(
+                Self::parsed_env_var_or("SLACK_CONNECT_TIMEOUT_SECS", 10),
+                10,
+            )),
+            slack_request_timeout: Duration::from_secs(errors.check(
+                Self::parsed_env_var_or("SLACK_REQUEST_TIMEOUT_SECS", 30),
+                30,
+            )),
+            command_aliases: errors.check(Self::command_aliases_from_env(), HashMap::new()),
+            admin_user_ids: Self::list_env_var("ADMIN_USER_IDS"),
+            user_teams: errors.check(Self::user_teams_from_env(), HashMap::new()),
+            quick_log_reaction: Self::optional_env_var("QUICK_LOG_REACTION"),
+            ignore_reaction: Self::optional_env_var("IGNORE_REACTION")
+                .unwrap_or_else(|| "x".to_string()),
+            post_identity: errors.check(
+                Self::post_identity_from_env(),
+                PostIdentity::Contributor {
+                    suffix: String::new(),
+                },
+            ),
+            profile_image_size: errors
+                .check(Self::profile_image_size_from_env(), ImageSize::Largest),
+            attachment_title: Self::optional_env_var("ATTACHMENT_TITLE"),
+            attachment_footer: Self::optional_env_var("ATTACHMENT_FOOTER"),
+            attachment_footer_icon_url: Self::optional_env_var("ATTACHMENT_FOOTER_ICON_URL"),
+            success_message: errors.check(Self::success_message_from_env(), None),
+            medium_effort_hours: errors
+                .check(Self::parsed_env_var_or("MEDIUM_EFFORT_HOURS", 4.0), 4.0),
+            large_effort_hours: errors
+                .check(Self::parsed_env_var_or("LARGE_EFFORT_HOURS", 8.0), 8.0),
+            max_hours_per_entry: errors
+                .check(Self::parsed_env_var_or("MAX_HOURS_PER_ENTRY", 24.0), 24.0),
+            require_description: errors
+                .check(Self::parsed_env_var_or("REQUIRE_DESCRIPTION", true), true),
+            min_description_length: errors
+                .check(Self::parsed_env_var_or("MIN_DESCRIPTION_LENGTH", 0), 0),
+            max_description_length: errors.check(
+                Self::parsed_env_var_or("MAX_DESCRIPTION_LENGTH", 1000),
+                1000,
+            ),
+            stats_description_length: errors
+                .check(Self::parsed_env_var_or("STATS_DESCRIPTION_LENGTH", 80), 80),
+            unique_url_credit: errors.check(
+                Self::unique_url_credit_from_env(),
+                models::UniqueUrlCredit::Earliest,
+            ),
+            features: errors.check(Self::features_from_env(), FeatureFlags::DEFAULT),
+            co_author_hours: errors.check(
+                Self::co_author_hours_from_env(),
+                models::HoursAttribution::Duplicate,
+            ),
+            record_rate_limit: errors.check(Self::parsed_env_var_or("RECORD_RATE_LIMIT", 5), 5),
+            history_max_pages: errors.check(Self::parsed_env_var_or("HISTORY_MAX_PAGES", 50), 50),
+            allowed_url_hosts: Self::list_env_var("ALLOWED_URL_HOSTS")
+                .into_iter()
+                .map(|host| host.to_lowercase())
+                .collect(),
+            verify_url_reachable: errors.check(
+                Self::parsed_env_var_or("VERIFY_URL_REACHABLE", false),
+                false,
+            ),
+            loading_messages_path: Self::optional_env_var("LOADING_MESSAGES_PATH"),
+            random_loading_messages: errors.check(
+                Self::parsed_env_var_or("RANDOM_LOADING_MESSAGES", true),
+                true,
+            ),
+            loading_message_prefix: Self::optional_env_var("LOADING_MESSAGE_PREFIX")
+                .unwrap_or_else(|| "Please wait...".to_string()),
+            // Accepts both `hourglass` and `:hourglass:`
+            loading_message_emoji: Self::optional_env_var("LOADING_MESSAGE_EMOJI")
+                .map(|emoji| emoji.trim().trim_matches(':').to_string())
+                .filter(|emoji| !emoji.is_empty()),
+            webhook_url: Self::optional_env_var("WEBHOOK_URL"),
+            webhook_secret: Self::optional_env_var("WEBHOOK_SECRET"),
+            admin_api_secret: Self::optional_env_var("ADMIN_API_SECRET")
+                .filter(|secret| !secret.trim().is_empty()),
+            log_format: errors.check(Self::log_format_from_env(), LogFormat::Text),
+            dry_run: errors.check(Self::parsed_env_var_or("DRY_RUN", false), false),
+            digest_schedule: errors.check(Self::digest_schedule_from_env(), None),
+        };
+
+        errors.into_result()?;
+        Ok(config)
+    }
+
+    pub fn is_admin(&self, user_id: &SlackUserId) -> bool {
+        self.admin_user_ids.contains(&user_id.0)
+    }
+
+    pub fn is_url_host_allowed(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.allowed_url_hosts.is_empty()
+            || self
+                .allowed_url_hosts
+                .iter()
+                .any(|allowed| host == *allowed || host.ends_with(&format!(".{allowed}")))
+    }
+
+    fn port_from_env() -> Result<u16, anyhow::Error> {
+        let Some(port) = Self::optional_env_var("PORT") else {
+            return Ok(DEFAULT_PORT);
+        };
+
+        match port.parse::<u16>() {
+            Ok(port) if port > 0 => Ok(port),
+            _ => Err(anyhow!(
+                "Invalid PORT '{port}', expected a number between 1 and 65535"
+            )),
+        }
+    }
+
+    /// Reads aliases in the form `log=record,add=record`. Aliases can only point to
+    /// actual subcommands, see [`request_handlers::SUBCOMMANDS`].
+    fn command_aliases_from_env() -> Result<HashMap<String, String>, anyhow::Error> {
+        let aliases = Self::optional_env_var("COMMAND_ALIASES")
+            .unwrap_or_else(|| "log=record,add=record".to_string());
+
+        let mut result = HashMap::new();
+        for alias in aliases.split(',').filter(|alias| !alias.trim().is_empty()) {
+            let Some((word, subcommand)) = alias.split_once('=') else {
+                return Err(anyhow!(
+                    "Invalid COMMAND_ALIASES entry '{alias}', expected e.g. 'log=record'"
+                ));
+            };
+            let subcommand = subcommand.trim().to_lowercase();
+            if !request_handlers::is_subcommand(&subcommand) {
+                let words: Vec<&str> = request_handlers::SUBCOMMANDS
+                    .iter()
+                    .map(|subcommand| subcommand.word)
+                    .collect();
+                return Err(anyhow!(
+                    "Invalid COMMAND_ALIASES entry '{alias}', expected one of {} after '='",
+                    words.join(", ")
+                ));
+            }
+            result.insert(word.trim().to_lowercase(), subcommand);
+        }
+
+        Ok(result)
+    }
+
+    /// Reads teams in the form `U0123456789=Platform,U9876543210=Frontend`
+    fn user_teams_from_env() -> Result<HashMap<String, String>, anyhow::Error> {
+        let mut result = HashMap::new();
+        for mapping in Self::list_env_var("USER_TEAMS") {
+            match mapping.split_once('=') {
+                Some((user_id, team)) if !team.trim().is_empty() => {
+                    result.insert(user_id.trim().to_string(), team.trim().to_string());
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid USER_TEAMS entry '{mapping}', expected e.g. 'U0123456789=Platform'"
+                    ))
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn storage_backend_from_env() -> Result<store::StorageBackend, anyhow::Error> {
+        match Self::optional_env_var("STORAGE_BACKEND").as_deref() {
+            None | Some("redis") => Ok(store::StorageBackend::Redis),
+            Some("memory") => Ok(store::StorageBackend::Memory),
+            Some(other) => Err(anyhow!(
+                "Invalid STORAGE_BACKEND '{other}', expected 'redis' or 'memory'"
+            )),
+        }
+    }
+
+    fn post_identity_from_env() -> Result<PostIdentity, anyhow::Error> {
+        match Self::optional_env_var("POST_IDENTITY").as_deref() {
+            None | Some("contributor") => Ok(PostIdentity::Contributor {
+                // May be set to an empty string to post under the contributor's name only
+                suffix: Self::optional_env_var("POST_USERNAME_SUFFIX")
+                    .unwrap_or_else(|| " via Wizard of OSS".to_string()),
+            }),
+            Some("single") => Ok(PostIdentity::Single {
+                username: Self::optional_env_var("POST_USERNAME"),
+                icon_url: Self::optional_env_var("POST_ICON_URL"),
+            }),
+            Some(other) => Err(anyhow!(
+                "Invalid POST_IDENTITY '{other}', expected 'contributor' or 'single'"
+            )),
+        }
+    }
+
+    fn profile_image_size_from_env() -> Result<ImageSize, anyhow::Error> {
+        match Self::optional_env_var("PROFILE_IMAGE_SIZE").as_deref() {
+            None | Some("largest") => Ok(ImageSize::Largest),
+            Some("smallest") => Ok(ImageSize::Smallest),
+            Some(other) => other.parse().map(ImageSize::Exact).map_err(|_| {
+                anyhow!(
+                    "Invalid PROFILE_IMAGE_SIZE '{other}', expected 'smallest', 'largest' \
+                     or a size in pixels"
+                )
+            }),
+        }
+    }
+
+    fn success_message_from_env() -> Result<Option<String>, anyhow::Error> {
+        let Some(template) = Self::optional_env_var("SUCCESS_MESSAGE") else {
+            return Ok(None);
+        };
+
+        message_template::validate(&template).context("Invalid SUCCESS_MESSAGE")?;
+        Ok(Some(template))
+    }
+
+    fn unique_url_credit_from_env() -> Result<models::UniqueUrlCredit, anyhow::Error> {
+        match Self::optional_env_var("UNIQUE_URL_CREDIT").as_deref() {
+            None | Some("earliest") => Ok(models::UniqueUrlCredit::Earliest),
+            Some("latest") => Ok(models::UniqueUrlCredit::Latest),
+            Some(other) => Err(anyhow!(
+                "Invalid UNIQUE_URL_CREDIT '{other}', expected 'earliest' or 'latest'"
+            )),
+        }
+    }
+
+    fn co_author_hours_from_env() -> Result<models::HoursAttribution, anyhow::Error> {
+        match Self::optional_env_var("CO_AUTHOR_HOURS").as_deref() {
+            None | Some("duplicate") => Ok(models::HoursAttribution::Duplicate),
+            Some("split") => Ok(models::HoursAttribution::Split),
+            Some(other) => Err(anyhow!(
+                "Invalid CO_AUTHOR_HOURS '{other}', expected 'duplicate' or 'split'"
+            )),
+        }
+    }
+
+    /// Reads the enabled features, e.g. `reactions,trend`. If the variable is set, only
+    /// the listed features are enabled, and all others are disabled.
+    fn features_from_env() -> Result<FeatureFlags, anyhow::Error> {
+        if Self::optional_env_var("WOSS_FEATURES").is_none() {
+            return Ok(FeatureFlags::DEFAULT);
+        }
+
+        let mut features = FeatureFlags {
+            reactions: false,
+            project_threads: false,
+            trend: false,
+        };
+        for name in Self::list_env_var("WOSS_FEATURES") {
+            match name.to_lowercase().as_str() {
+                "reactions" => features.reactions = true,
+                "project_threads" => features.project_threads = true,
+                "trend" => features.trend = true,
+                _ => {
+                    return Err(anyhow!(
+                        "Unknown feature '{name}' in WOSS_FEATURES, expected any of {}",
+                        FeatureFlags::NAMES.join(", ")
+                    ))
+                }
+            }
+        }
+
+        Ok(features)
+    }
+
+    fn log_format_from_env() -> Result<LogFormat, anyhow::Error> {
+        match Self::optional_env_var("LOG_FORMAT").as_deref() {
+            None | Some("text") => Ok(LogFormat::Text),
+            Some("json") => Ok(LogFormat::Json),
+            Some(other) => Err(anyhow!(
+                "Invalid LOG_FORMAT '{other}', expected 'text' or 'json'"
+            )),
+        }
+    }
+
+    fn digest_schedule_from_env() -> Result<Option<digest::DigestSchedule>, anyhow::Error> {
+        let Some(weekday) = Self::optional_env_var("DIGEST_WEEKDAY") else {
+            return Ok(None);
+        };
+
+        let weekday = weekday
+            .parse::<Weekday>()
+            .map_err(|_| anyhow!("Invalid DIGEST_WEEKDAY '{weekday}', expected e.g. 'mon'"))?;
+        let time = Self::optional_env_var("DIGEST_TIME").unwrap_or_else(|| "09:00".to_string());
+        let time = NaiveTime::parse_from_str(&time, "%H:%M")
+            .with_context(|| format!("Invalid DIGEST_TIME '{time}', expected e.g. '09:00'"))?;
+        let channel_id = Self::optional_env_var("DIGEST_CHANNEL_ID")
+            .map(Ok)
+            .unwrap_or_else(|| Self::env_var("SLACK_OSS_CHANNEL_ID"))?;
+        let mode = match Self::optional_env_var("DIGEST_MODE").as_deref() {
+            None | Some("post") => digest::DigestMode::Post,
+            Some("scoreboard") => digest::DigestMode::Scoreboard,
+            Some(other) => {
+                return Err(anyhow!(
+                    "Invalid DIGEST_MODE '{other}', expected 'post' or 'scoreboard'"
+                ))
+            }
+        };
+
+        Ok(Some(digest::DigestSchedule {
+            weekday,
+            time,
+            channel_id,
+            mode,
+        }))
+    }
+
+    /// A comma separated list, which is empty if the variable isn't set
+    fn list_env_var(name: &str) -> Vec<String> {
+        Self::optional_env_var(name)
+            .map(|list| {
+                list.split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     fn env_var(name: &str) -> Result<String, anyhow::Error> {
-        std::env::var(name).with_context(|| format!("Couldn't find environment variable {}", name))
+        std::env::var(name).with_context(|| format!("Environment variable {} is not set", name))
+    }
+
+    /// Same as [`AppConfig::env_var`], but also rejects blank values. Otherwise the server
+    /// would start, but fail to verify or authenticate every request.
+    fn secret_env_var(name: &str) -> Result<String, anyhow::Error> {
+        let value = Self::env_var(name)?;
+        if value.trim().is_empty() {
+            return Err(anyhow!("Environment variable {name} is set, but empty"));
+        }
+        Ok(value)
+    }
+
+    fn optional_env_var(name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn parsed_env_var_or<T>(name: &str, default: T) -> Result<T, anyhow::Error>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        match Self::optional_env_var(name) {
+            Some(value) => value
+                .parse()
+                .with_context(|| format!("Invalid value for environment variable {}", name)),
+            None => Ok(default),
+        }
+    }
+
+    /// The defaults of [`AppConfig::from_env`], with the required variables set to
+    /// placeholders and the data kept in memory
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        AppConfig {
+            port: DEFAULT_PORT,
+            storage_backend: store::StorageBackend::Memory,
+            allow_degraded_start: false,
+            redis_url: String::new(),
+            redis_namespace: "woss".to_string(),
+            slack_client_id: "client-id".to_string(),
+            slack_client_secret: "client-secret".to_string(),
+            slack_bot_scope: "commands,chat:write".to_string(),
+            slack_redirect_host: "http://localhost:8080".to_string(),
+            slack_signing_secret: "signing-secret".to_string(),
+            slack_test_token: "xoxb-test".to_string(),
+            slack_oss_channel_id: "COSS".to_string(),
+            slack_success_reaction: "tada".to_string(),
+            slack_connect_timeout: Duration::from_secs(10),
+            slack_request_timeout: Duration::from_secs(30),
+            command_aliases: HashMap::from([
+                ("log".to_string(), "record".to_string()),
+                ("add".to_string(), "record".to_string()),
+            ]),
+            admin_user_ids: vec!["UADMIN".to_string()],
+            user_teams: HashMap::new(),
+            ignore_reaction: "x".to_string(),
+            quick_log_reaction: None,
+            post_identity: PostIdentity::Contributor {
+                suffix: String::new(),
+            },
+            profile_image_size: ImageSize::Largest,
+            attachment_title: None,
+            attachment_footer: None,
+            attachment_footer_icon_url: None,
+            success_message: None,
+            medium_effort_hours: 4.0,
+            large_effort_hours: 8.0,
+            max_hours_per_entry: 24.0,
+            require_description: true,
+            min_description_length: 0,
+            max_description_length: 1000,
+            stats_description_length: 80,
+            unique_url_credit: models::UniqueUrlCredit::Earliest,
+            features: FeatureFlags::DEFAULT,
+            co_author_hours: models::HoursAttribution::Duplicate,
+            record_rate_limit: 5,
+            history_max_pages: 50,
+            allowed_url_hosts: vec![],
+            verify_url_reachable: false,
+            loading_messages_path: None,
+            // Keeps the loading message predictable
+            random_loading_messages: false,
+            loading_message_prefix: "Please wait...".to_string(),
+            loading_message_emoji: None,
+            webhook_url: None,
+            webhook_secret: None,
+            admin_api_secret: None,
+            log_format: LogFormat::Text,
+            dry_run: false,
+            digest_schedule: None,
+        }
+    }
+}
+
+/// Collects the errors of reading the configuration, so that they can be reported together
+#[derive(Default)]
+struct ConfigErrors(Vec<anyhow::Error>);
+
+impl ConfigErrors {
+    /// Returns the value, or records the error and returns the fallback. The fallback is
+    /// never used, as [`ConfigErrors::into_result`] fails if any error was recorded.
+    fn check<T>(&mut self, result: Result<T, anyhow::Error>, fallback: T) -> T {
+        result.unwrap_or_else(|err| {
+            self.0.push(err);
+            fallback
+        })
+    }
+
+    fn env_var(&mut self, name: &str) -> String {
+        self.check(AppConfig::env_var(name), String::new())
+    }
+
+    fn secret_env_var(&mut self, name: &str) -> String {
+        self.check(AppConfig::secret_env_var(name), String::new())
+    }
+
+    fn into_result(self) -> Result<(), anyhow::Error> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+
+        let mut messages: Vec<String> = vec![];
+        for err in &self.0 {
+            // E.g. SLACK_OSS_CHANNEL_ID is also needed for the digest
+            let message = format!("- {err:#}");
+            if !messages.contains(&message) {
+                messages.push(message);
+            }
+        }
+
+        Err(anyhow!(
+            "The configuration is invalid:\n{}",
+            messages.join("\n")
+        ))
     }
 }
 
@@ -69,11 +706,24 @@ impl AppConfig {
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let config = AppConfig::from_env()?;
 
-    let subscriber = tracing_subscriber::fmt()
-        .with_env_filter("slack_morphism=debug,oss_bot=trace")
-        .finish();
+    // RUST_LOG takes precedence over the default filter
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("slack_morphism=debug,oss_bot=trace"));
+
+    match config.log_format {
+        LogFormat::Text => {
+            let subscriber = tracing_subscriber::fmt().with_env_filter(filter).finish();
+            tracing::subscriber::set_global_default(subscriber)?;
+        }
+        LogFormat::Json => {
+            let subscriber = tracing_subscriber::fmt()
+                .json()
+                .with_env_filter(filter)
+                .finish();
+            tracing::subscriber::set_global_default(subscriber)?;
+        }
+    }
 
-    tracing::subscriber::set_global_default(subscriber)?;
     server::start(config).await?;
 
     Ok(())
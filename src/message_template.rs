@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+
+use crate::models::{self, OpenSourceAttachment};
+
+/// The placeholders that can be used in message templates, e.g. `{user}`
+pub const PLACEHOLDERS: [&str; 5] = ["user", "hours", "url", "project", "country"];
+
+/// Makes sure that the template only uses known placeholders, so that a typo is caught on
+/// startup rather than showing up in posted messages.
+pub fn validate(template: &str) -> anyhow::Result<()> {
+    let mut rest = template;
+    while let Some((_, name, after)) = next_placeholder(rest) {
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(anyhow!(
+                "Unknown placeholder '{{{name}}}', expected one of {}",
+                PLACEHOLDERS.map(|name| format!("{{{name}}}")).join(", ")
+            ));
+        }
+        rest = after;
+    }
+
+    Ok(())
+}
+
+/// Replaces the placeholders in the template with the values of the entry
+pub fn render(template: &str, entry: &OpenSourceAttachment) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some((before, name, after)) = next_placeholder(rest) {
+        result.push_str(before);
+        match placeholder_value(name, entry) {
+            Some(value) => result.push_str(&value),
+            // Can't happen for validated templates
+            None => result.push_str(&format!("{{{name}}}")),
+        }
+        rest = after;
+    }
+    result.push_str(rest);
+
+    result
+}
+
+fn placeholder_value(name: &str, entry: &OpenSourceAttachment) -> Option<String> {
+    match name {
+        "user" => Some(entry.username.clone()),
+        "hours" => Some(models::display_hours(entry.number_of_hours)),
+        "url" => Some(entry.url.to_string()),
+        "project" => Some(entry.project_name()),
+        "country" => Some(entry.country.clone()),
+        _ => None,
+    }
+}
+
+/// Splits the text at the next placeholder into the text before it, its name, and the
+/// text after it
+fn next_placeholder(text: &str) -> Option<(&str, &str, &str)> {
+    let start = text.find('{')?;
+    let length = text[start + 1..].find('}')?;
+    let name = &text[start + 1..start + 1 + length];
+
+    Some((&text[..start], name, &text[start + length + 2..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hours_are_formatted() {
+        let entry = OpenSourceAttachment {
+            username: "jane".to_string(),
+            number_of_hours: 4.0 / 3.0,
+            country: "germany".to_string(),
+            url: "https://github.com/x3ro/wizard-of-oss/pull/1"
+                .parse()
+                .unwrap(),
+            project: None,
+            description: String::new(),
+            tags: vec![],
+            co_authors: vec![],
+            submitted_at: None,
+        };
+
+        assert_eq!(
+            render("{user} spent {hours} hours on {project}", &entry),
+            "jane spent 1.33 hours on x3ro/wizard-of-oss"
+        );
+    }
+}
@@ -1,14 +1,301 @@
 use anyhow::{format_err, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use slack_morphism::SlackMessageAttachmentFieldObject;
+use tracing::debug;
 use url::Url;
 
-#[derive(Debug, Clone)]
+/// An entry as posted to Slack. The JSON representation is used to store entries in
+/// redis and to send them to the webhook. Fields that are optional in the attachment
+/// are optional in JSON as well, so that entries stored by older versions can be read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OpenSourceAttachment {
     pub username: String,
-    pub number_of_hours: i16,
+    pub number_of_hours: f64,
     pub country: String,
     pub url: Url,
+    /// The project as entered by the user, see [`OpenSourceAttachment::project_name`]
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
     pub description: String,
+    /// Categories of the contribution, such as "docs" or "bugfix"
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Usernames of the people the author worked with, e.g. when pair programming. They
+    /// are credited with hours as well, see [`OpenSourceAttachment::credited_hours`].
+    #[serde(default)]
+    pub co_authors: Vec<String>,
+    /// When the entry was submitted. This is not part of the attachment fields, but
+    /// stored alongside the entry in redis. For entries read from Slack, it is derived
+    /// from the timestamp of the message the attachment belongs to.
+    #[serde(default)]
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+impl OpenSourceAttachment {
+    /// A short name for the project the contribution was made to. Unless the user entered
+    /// a project explicitly, it is derived from the URL: For GitHub URLs this is
+    /// `owner/repo`, for all other URLs it is the host name.
+    pub fn project_name(&self) -> String {
+        if let Some(project) = &self.project {
+            return project.clone();
+        }
+
+        let host = self.url.host_str().unwrap_or_default();
+        let host = host.strip_prefix("www.").unwrap_or(host);
+
+        if host == "github.com" {
+            let segments: Vec<&str> = self
+                .url
+                .path_segments()
+                .map(|segments| segments.filter(|s| !s.is_empty()).take(2).collect())
+                .unwrap_or_default();
+
+            // GitHub owners and repositories are case insensitive
+            if segments.len() == 2 {
+                return segments.join("/").to_lowercase();
+            }
+        }
+
+        host.to_string()
+    }
+
+    /// The URL without scheme, query, fragment and trailing slash, so that different
+    /// spellings of the same link are recognized, e.g. when a PR is logged twice
+    pub fn normalized_url(&self) -> String {
+        normalize_url(&self.url)
+    }
+
+    pub fn has_contributor(&self, username: &str) -> bool {
+        self.username == username || self.co_authors.iter().any(|name| name == username)
+    }
+
+    /// The hours credited to the author and each co-author. Entries without co-authors
+    /// credit all hours to the author, regardless of the attribution.
+    pub fn credited_hours(&self, attribution: HoursAttribution) -> Vec<(&str, f64)> {
+        let contributors: Vec<&str> = std::iter::once(self.username.as_str())
+            .chain(self.co_authors.iter().map(String::as_str))
+            .collect();
+        let hours = match attribution {
+            HoursAttribution::Duplicate => self.number_of_hours,
+            // Rounded, so that e.g. a third of an hour doesn't show up with 16 digits
+            HoursAttribution::Split => {
+                (self.number_of_hours / contributors.len() as f64 * 100.0).round() / 100.0
+            }
+        };
+
+        contributors
+            .into_iter()
+            .map(|username| (username, hours))
+            .collect()
+    }
+
+    /// The hours credited to the given user, 0 if they didn't contribute
+    pub fn hours_credited_to(&self, username: &str, attribution: HoursAttribution) -> f64 {
+        self.credited_hours(attribution)
+            .into_iter()
+            .find(|(name, _)| *name == username)
+            .map_or(0.0, |(_, hours)| hours)
+    }
+}
+
+/// How the hours of an entry are credited if several people worked on it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HoursAttribution {
+    /// Everyone is credited with all hours, since everyone spent that time
+    Duplicate,
+    /// The hours are divided evenly
+    Split,
+}
+
+/// The hours per contributor of all entries, which is expensive to compute for large
+/// channels and thus computed on demand, see `slack::recompute_leaderboard`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub computed_at: DateTime<Utc>,
+    /// Sorted by hours in descending order
+    pub hours_by_user: Vec<(String, f64)>,
+    /// Same as `hours_by_user`, but counting each URL only once. Missing in leaderboards
+    /// computed before it was introduced.
+    #[serde(default)]
+    pub unique_hours_by_user: Option<Vec<(String, f64)>>,
+}
+
+/// Which entry counts if the same URL was logged several times, see
+/// `slack::unique_by_url`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniqueUrlCredit {
+    Earliest,
+    Latest,
+}
+
+/// Serializes the given entries to CSV, including a header row.
+pub fn entries_to_csv(entries: &[OpenSourceAttachment]) -> anyhow::Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record([
+        "author",
+        "hours",
+        "country",
+        "project",
+        "url",
+        "description",
+        "date",
+        "co_authors",
+    ])?;
+
+    for entry in entries {
+        writer.write_record([
+            entry.username.clone(),
+            entry.number_of_hours.to_string(),
+            entry.country.clone(),
+            entry.project_name(),
+            entry.url.to_string(),
+            entry.description.clone(),
+            entry
+                .submitted_at
+                .map(|date| date.to_rfc3339())
+                .unwrap_or_default(),
+            entry.co_authors.join(", "),
+        ])?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Parses a user-provided number of hours, being lenient about the format: Trailing
+/// text without digits (e.g. "4 hours") is ignored, and a comma is accepted as decimal
+/// separator (e.g. "4,5") since that's what many European locales use. Durations such
+/// as "1:30" or "1h30m" are accepted as well, see [`parse_duration`] and
+/// [`parse_duration_with_units`]. Like those, the result is rounded to two decimals.
+/// Returns `None` if the input doesn't start with a number, or if the number contains
+/// several separators or looks like it uses a thousands separator, e.g. "1,000".
+pub fn parse_hours(input: &str) -> Option<f64> {
+    if input.contains(':') {
+        return parse_duration(input);
+    }
+    if let Some(hours) = parse_duration_with_units(input) {
+        return Some(hours);
+    }
+
+    let input = input.trim();
+    let number_length = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(input.len());
+    let (number, rest) = input.split_at(number_length);
+    // E.g. "1h90m" or "2 hours 30 minutes", which would otherwise be read as 1 or 2 hours
+    if rest.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut parts = number.split(['.', ',']);
+    let (integer, decimals) = (parts.next()?, parts.next());
+    if parts.next().is_some() {
+        return None;
+    }
+    // "1,000" or "1.000" are likely meant as a thousand, "0.125" can't be
+    if decimals.map_or(false, |decimals| decimals.len() == 3) && integer != "0" {
+        return None;
+    }
+
+    let hours = number.replace(',', ".").parse::<f64>().ok()?;
+    Some((hours * 100.0).round() / 100.0)
+}
+
+/// Formats a number of hours with at most two decimals and without trailing zeros, e.g.
+/// "1.5". Sums of hours such as 0.1 + 0.2 would otherwise be shown with 16 digits.
+pub fn display_hours(hours: f64) -> String {
+    let formatted = format!("{hours:.2}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Parses a duration in the form `H:MM`, e.g. "1:30" for 1.5 hours. The result is
+/// rounded to two decimals, so that e.g. "1:20" is stored as 1.33 rather than with
+/// 16 digits. Returns `None` if the minutes aren't between 0 and 59.
+fn parse_duration(input: &str) -> Option<f64> {
+    let (hours, minutes) = input.trim().split_once(':')?;
+    let is_number = |value: &str| !value.is_empty() && value.chars().all(|c| c.is_ascii_digit());
+    if !is_number(hours) || !is_number(minutes) || minutes.len() != 2 {
+        return None;
+    }
+
+    let minutes: u32 = minutes.parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+
+    let hours = hours.parse::<u32>().ok()? as f64 + minutes as f64 / 60.0;
+    Some((hours * 100.0).round() / 100.0)
+}
+
+/// Parses a duration with units, e.g. "1h30m", "2h" or "90m", ignoring whitespace and
+/// case. Returns `None` if the input isn't in this form, e.g. for "4 hours", which
+/// [`parse_hours`] reads as a plain number instead. Like [`parse_duration`], the result
+/// is rounded to two decimals, and minutes after hours have to be less than 60.
+fn parse_duration_with_units(input: &str) -> Option<f64> {
+    let compact: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    let strip_minutes = |value: &str| -> Option<u32> {
+        let minutes = ["minutes", "minute", "mins", "min", "m"]
+            .into_iter()
+            .find_map(|unit| value.strip_suffix(unit))?;
+        if minutes.is_empty() || !minutes.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        minutes.parse().ok()
+    };
+
+    let (hours, minutes) = match compact.split_once('h') {
+        Some((hours, "")) => (hours.replace(',', "."), 0),
+        Some((hours, minutes)) => {
+            let minutes = strip_minutes(minutes).filter(|minutes| *minutes < 60)?;
+            (hours.to_string(), minutes)
+        }
+        None => ("0".to_string(), strip_minutes(&compact)?),
+    };
+    if hours.is_empty() || !hours.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    let hours = hours.parse::<f64>().ok()? + f64::from(minutes) / 60.0;
+    Some((hours * 100.0).round() / 100.0)
+}
+
+/// See [`OpenSourceAttachment::normalized_url`]
+pub fn normalize_url(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    format!("{host}{}", url.path().trim_end_matches('/'))
+}
+
+/// Parses a URL as it appears in a message. Slack turns URLs into links by wrapping them
+/// in pointy brackets, optionally with a display text after a pipe, e.g.
+/// `<https://example.com|example.com>`.
+pub fn parse_slack_url(value: &str) -> anyhow::Result<Url> {
+    let trimmed = value.trim();
+    let link = trimmed
+        .strip_prefix('<')
+        .and_then(|link| link.strip_suffix('>'))
+        .unwrap_or(trimmed);
+    // URLs can't contain a literal pipe, Slack escapes it as %7C
+    let url = link.split('|').next().unwrap_or(link);
+
+    Url::parse(url).context(value.to_string())
+}
+
+/// Finds the first HTTP(S) link in the text of a message, see [`parse_slack_url`]
+pub fn find_slack_url(text: &str) -> Option<Url> {
+    text.split('<')
+        .skip(1)
+        .filter_map(|link| link.split_once('>'))
+        .filter_map(|(link, _)| parse_slack_url(&format!("<{link}>")).ok())
+        .find(|url| url.scheme() == "http" || url.scheme() == "https")
 }
 
 impl TryFrom<Vec<SlackMessageAttachmentFieldObject>> for OpenSourceAttachment {
@@ -19,7 +306,12 @@ impl TryFrom<Vec<SlackMessageAttachmentFieldObject>> for OpenSourceAttachment {
         let mut number_of_hours = Err(format_err!("missing number of hours"));
         let mut country = Err(format_err!("missing country"));
         let mut url = Err(format_err!("missing url"));
-        let mut description = Err(format_err!("missing description"));
+        // Descriptions are optional if configured so, see `AppConfig::require_description`
+        let mut description = String::new();
+        // Tags are optional, and entries posted before they were introduced don't have any
+        let mut tags = vec![];
+        let mut co_authors = vec![];
+        let mut project = None;
 
         for field in &fields {
             let Some(title) = &field.title else {
@@ -32,17 +324,16 @@ impl TryFrom<Vec<SlackMessageAttachmentFieldObject>> for OpenSourceAttachment {
 
             match title.as_str() {
                 "Author" => username = Ok(value.clone()),
-                "Time" => number_of_hours = value.parse::<i16>().context(value.clone()),
+                "Time" => number_of_hours = value.parse::<f64>().context(value.clone()),
                 "Office" => country = Ok(value.clone()),
-                "URL" => {
-                    url = {
-                        // Slack adds pointy brackets around the URL, for formatting reasons apparently
-                        let trimmed = value.trim_start_matches('<').trim_end_matches('>');
-                        Url::parse(trimmed).context(value.clone())
-                    }
-                }
-                "Description" => description = Ok(value.clone()),
-                title => Err(format_err!("unknown field name '{title}'"))?,
+                "URL" => url = parse_slack_url(value),
+                "Description" => description = value.clone(),
+                "Project" => project = Some(value.clone()),
+                "Tags" => tags = split_list(value),
+                "Co-authors" => co_authors = split_list(value),
+                // Ignoring unknown fields allows us to add new fields to the attachment
+                // without breaking the parsing of messages posted by other versions
+                title => debug!("Ignoring unknown attachment field '{title}'"),
             }
         }
 
@@ -51,14 +342,27 @@ impl TryFrom<Vec<SlackMessageAttachmentFieldObject>> for OpenSourceAttachment {
             number_of_hours: number_of_hours?,
             country: country?,
             url: url?,
-            description: description?,
+            project,
+            description,
+            tags,
+            co_authors,
+            submitted_at: None,
         })
     }
 }
 
+/// Splits a comma separated field value, as written by the conversion below
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
 impl From<OpenSourceAttachment> for Vec<SlackMessageAttachmentFieldObject> {
     fn from(value: OpenSourceAttachment) -> Self {
-        vec![
+        let mut fields = vec![
             SlackMessageAttachmentFieldObject {
                 title: Some("Author".into()),
                 value: Some(value.username),
@@ -66,7 +370,7 @@ impl From<OpenSourceAttachment> for Vec<SlackMessageAttachmentFieldObject> {
             },
             SlackMessageAttachmentFieldObject {
                 title: Some("Time".into()),
-                value: Some(value.number_of_hours.to_string()),
+                value: Some(display_hours(value.number_of_hours)),
                 short: Some(true),
             },
             SlackMessageAttachmentFieldObject {
@@ -79,11 +383,294 @@ impl From<OpenSourceAttachment> for Vec<SlackMessageAttachmentFieldObject> {
                 value: Some(value.url.to_string()),
                 short: Some(true),
             },
-            SlackMessageAttachmentFieldObject {
+        ];
+
+        if let Some(project) = value.project {
+            fields.push(SlackMessageAttachmentFieldObject {
+                title: Some("Project".into()),
+                value: Some(project),
+                short: Some(true),
+            });
+        }
+
+        if !value.tags.is_empty() {
+            fields.push(SlackMessageAttachmentFieldObject {
+                title: Some("Tags".into()),
+                value: Some(value.tags.join(", ")),
+                short: Some(true),
+            });
+        }
+
+        if !value.co_authors.is_empty() {
+            fields.push(SlackMessageAttachmentFieldObject {
+                title: Some("Co-authors".into()),
+                value: Some(value.co_authors.join(", ")),
+                short: Some(true),
+            });
+        }
+
+        if !value.description.is_empty() {
+            fields.push(SlackMessageAttachmentFieldObject {
                 title: Some("Description".into()),
                 value: Some(value.description),
                 short: Some(false),
-            },
-        ]
+            });
+        }
+
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// URLs with optional paths and query strings, without characters that Slack escapes
+    const URL_PATTERN: &str = concat!(
+        "https://[a-z]{1,10}\\.(com|org|io)",
+        "(/[a-zA-Z0-9_.-]{1,8}){0,3}",
+        "(\\?[a-z]{1,5}=[a-z0-9]{0,5}(&[a-z]{1,5}=[a-z0-9]{0,5}){0,2})?",
+    );
+
+    fn url_strategy() -> impl Strategy<Value = Url> {
+        URL_PATTERN.prop_map(|url| Url::parse(&url).unwrap())
+    }
+
+    /// Items of comma separated fields, which can't contain commas themselves
+    fn list_strategy() -> impl Strategy<Value = Vec<String>> {
+        prop::collection::vec("[a-z][a-z0-9_-]{0,10}", 0..4)
+    }
+
+    fn attachment_strategy() -> impl Strategy<Value = OpenSourceAttachment> {
+        (
+            "[a-z][a-z0-9._-]{0,20}",
+            // Hours are rounded to two decimals when they are entered
+            (0u32..100_000).prop_map(|cents| f64::from(cents) / 100.0),
+            "[a-z_]{1,20}",
+            url_strategy(),
+            proptest::option::of("\\PC{1,20}"),
+            "\\PC{0,100}",
+            list_strategy(),
+            list_strategy(),
+        )
+            .prop_map(
+                |(
+                    username,
+                    number_of_hours,
+                    country,
+                    url,
+                    project,
+                    description,
+                    tags,
+                    co_authors,
+                )| {
+                    OpenSourceAttachment {
+                        username,
+                        number_of_hours,
+                        country,
+                        url,
+                        project,
+                        description,
+                        tags,
+                        co_authors,
+                        submitted_at: None,
+                    }
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn attachment_survives_round_trip(attachment in attachment_strategy()) {
+            let fields: Vec<SlackMessageAttachmentFieldObject> = attachment.clone().into();
+            prop_assert_eq!(OpenSourceAttachment::try_from(fields).unwrap(), attachment);
+        }
+
+        #[test]
+        fn slack_links_are_unwrapped(url in url_strategy(), text in "[a-z .]{0,20}") {
+            prop_assert_eq!(parse_slack_url(&format!("<{url}>")).unwrap(), url.clone());
+            prop_assert_eq!(parse_slack_url(&format!("<{url}|{text}>")).unwrap(), url);
+        }
+    }
+
+    #[test]
+    fn url_with_query_string_is_kept() {
+        let attachment = OpenSourceAttachment {
+            username: "jane".to_string(),
+            number_of_hours: 1.5,
+            country: "germany".to_string(),
+            url: Url::parse("https://github.com/x3ro/wizard-of-oss/pulls?q=is%3Aopen&page=2")
+                .unwrap(),
+            project: None,
+            description: String::new(),
+            tags: vec![],
+            co_authors: vec![],
+            submitted_at: None,
+        };
+
+        let mut fields: Vec<SlackMessageAttachmentFieldObject> = attachment.clone().into();
+        // Slack wraps links in pointy brackets when the message is read back
+        for field in &mut fields {
+            if field.title.as_deref() == Some("URL") {
+                field.value = field.value.as_ref().map(|url| format!("<{url}>"));
+            }
+        }
+
+        let parsed = OpenSourceAttachment::try_from(fields).unwrap();
+        assert_eq!(parsed, attachment);
+        assert_eq!(parsed.url.query(), Some("q=is%3Aopen&page=2"));
+    }
+
+    #[test]
+    fn hours_are_displayed_with_at_most_two_decimals() {
+        assert_eq!(display_hours(0.1 + 0.2), "0.3");
+        assert_eq!(display_hours(1.5), "1.5");
+        assert_eq!(display_hours(4.0 / 3.0), "1.33");
+        assert_eq!(display_hours(10.0), "10");
+        assert_eq!(display_hours(0.0), "0");
+    }
+
+    #[test]
+    fn slack_url_markup_is_removed() {
+        let url = Url::parse("https://github.com/x3ro/wizard-of-oss/pull/1").unwrap();
+
+        assert_eq!(parse_slack_url(url.as_str()).unwrap(), url);
+        assert_eq!(parse_slack_url(&format!(" <{url}> ")).unwrap(), url);
+        assert_eq!(parse_slack_url(&format!("<{url}|PR #1>")).unwrap(), url);
+        assert_eq!(
+            parse_slack_url("<https://example.com/search?q=a%7Cb&page=2|search>")
+                .unwrap()
+                .as_str(),
+            "https://example.com/search?q=a%7Cb&page=2"
+        );
+    }
+
+    #[test]
+    fn malformed_slack_urls_are_rejected() {
+        assert!(parse_slack_url("").is_err());
+        assert!(parse_slack_url("<>").is_err());
+        assert!(parse_slack_url("<|label>").is_err());
+        assert!(parse_slack_url("github.com/x3ro/wizard-of-oss").is_err());
+        // Unbalanced brackets aren't Slack markup, and not a valid URL either
+        assert!(parse_slack_url("<https://example.com").is_err());
+    }
+
+    #[test]
+    fn first_web_link_is_found_in_message() {
+        let text = "Reviewed <mailto:jane@example.com|jane> and \
+                    <https://github.com/x3ro/wizard-of-oss/pull/2|#2>, see <https://example.com>";
+        assert_eq!(
+            find_slack_url(text).unwrap().as_str(),
+            "https://github.com/x3ro/wizard-of-oss/pull/2"
+        );
+        assert_eq!(find_slack_url("No links here, <@U0000000001>"), None);
+    }
+
+    #[test]
+    fn hours_are_parsed_in_all_formats() {
+        assert_eq!(parse_hours("4"), Some(4.0));
+        assert_eq!(parse_hours("1.5"), Some(1.5));
+        assert_eq!(parse_hours("1,5"), Some(1.5));
+        assert_eq!(parse_hours(" 4 hours "), Some(4.0));
+        assert_eq!(parse_hours("1:30"), Some(1.5));
+        assert_eq!(parse_hours("0:45"), Some(0.75));
+        assert_eq!(parse_hours("1:20"), Some(1.33));
+        assert_eq!(parse_hours("1h30m"), Some(1.5));
+        assert_eq!(parse_hours("1h 30min"), Some(1.5));
+        assert_eq!(parse_hours("2H"), Some(2.0));
+        assert_eq!(parse_hours("1,5h"), Some(1.5));
+        assert_eq!(parse_hours("90m"), Some(1.5));
+        assert_eq!(parse_hours("45 min"), Some(0.75));
+        assert_eq!(parse_hours("1h 15 minutes"), Some(1.25));
+        assert_eq!(parse_hours("1.333333"), Some(1.33));
+        assert_eq!(parse_hours("0,125"), Some(0.13));
+    }
+
+    #[test]
+    fn invalid_hours_are_rejected() {
+        assert_eq!(parse_hours(""), None);
+        assert_eq!(parse_hours("a lot"), None);
+        assert_eq!(parse_hours("-2"), None);
+        assert_eq!(parse_hours("1:99"), None);
+        assert_eq!(parse_hours("1:5"), None);
+        assert_eq!(parse_hours(":30"), None);
+        assert_eq!(parse_hours("1h90m"), None);
+        assert_eq!(parse_hours("2 hours 30 minutes"), None);
+        assert_eq!(parse_hours("h30m"), None);
+        assert_eq!(parse_hours("m"), None);
+        assert_eq!(parse_hours("1,000"), None);
+        assert_eq!(parse_hours("2.500"), None);
+        assert_eq!(parse_hours("1.5.2"), None);
+        assert_eq!(parse_hours("1,5,"), None);
+    }
+
+    #[test]
+    fn unknown_attachment_fields_are_ignored() {
+        let field = |title: &str, value: &str| SlackMessageAttachmentFieldObject {
+            title: Some(title.to_string()),
+            value: Some(value.to_string()),
+            short: Some(true),
+        };
+        let fields = vec![
+            field("Author", "jane"),
+            field("Time", "2"),
+            // E.g. added by a newer version of the bot
+            field("Reviewer", "john"),
+            field("Office", "germany"),
+            field("URL", "<https://github.com/x3ro/wizard-of-oss/pull/1>"),
+        ];
+
+        let attachment = OpenSourceAttachment::try_from(fields).unwrap();
+        assert_eq!(attachment.username, "jane");
+        assert_eq!(attachment.number_of_hours, 2.0);
+        assert_eq!(attachment.country, "germany");
+        assert_eq!(
+            attachment.url.as_str(),
+            "https://github.com/x3ro/wizard-of-oss/pull/1"
+        );
+    }
+
+    #[test]
+    fn attachment_survives_json_round_trip() {
+        let attachment = OpenSourceAttachment {
+            username: "jane".to_string(),
+            number_of_hours: 1.5,
+            country: "germany".to_string(),
+            url: Url::parse("https://github.com/x3ro/wizard-of-oss/pull/1?w=1").unwrap(),
+            project: Some("Wizard of OSS".to_string()),
+            description: "Fixed the build".to_string(),
+            tags: vec!["bugfix".to_string()],
+            co_authors: vec!["john".to_string()],
+            submitted_at: Some("2023-01-02T10:00:00Z".parse().unwrap()),
+        };
+
+        let json = serde_json::to_value(&attachment).unwrap();
+        assert_eq!(
+            json["url"],
+            "https://github.com/x3ro/wizard-of-oss/pull/1?w=1"
+        );
+        assert_eq!(
+            serde_json::from_value::<OpenSourceAttachment>(json).unwrap(),
+            attachment
+        );
+    }
+
+    #[test]
+    fn entries_stored_by_older_versions_are_read() {
+        let json = r#"{
+            "username": "jane",
+            "number_of_hours": 2.0,
+            "country": "germany",
+            "url": "https://github.com/x3ro/wizard-of-oss/pull/1"
+        }"#;
+
+        let attachment: OpenSourceAttachment = serde_json::from_str(json).unwrap();
+        assert_eq!(attachment.project, None);
+        assert_eq!(attachment.description, "");
+        assert!(attachment.tags.is_empty());
+        assert!(attachment.co_authors.is_empty());
+        assert_eq!(attachment.submitted_at, None);
     }
 }
@@ -1,42 +1,152 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use redis::AsyncCommands;
-use slack_morphism::SlackUserId;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use slack_morphism::{SlackChannelId, SlackTeamId, SlackTs, SlackUserId};
 
 use crate::errors::AppError;
+use crate::models::{Leaderboard, OpenSourceAttachment};
+use crate::store::{self, Store};
 use crate::AppConfig;
 
+/// State of a rate limit, see [`Persistence::take_rate_limit_token`]
+#[derive(Debug, Serialize, Deserialize)]
+struct TokenBucket {
+    tokens: f64,
+    updated_at: DateTime<Utc>,
+}
+
+/// What [`Persistence::delete_user_data`] removed
+#[derive(Debug, Default)]
+pub struct DeletedUserData {
+    /// Names of the removed settings, e.g. "goal"
+    pub settings: Vec<&'static str>,
+    /// Number of removed entries the user authored
+    pub entries: usize,
+    /// Number of entries of others the user was removed from as co-author
+    pub co_authored_entries: usize,
+}
+
+/// Entries are keyed by the message they were posted as
+fn entry_key(channel: &SlackChannelId, ts: &SlackTs) -> String {
+    format!("{}:{}", channel.0, ts.0)
+}
+
+/// The inverse of [`entry_key`]. Channel IDs don't contain colons, timestamps may not.
+fn parse_entry_key(key: &str) -> Option<(SlackChannelId, SlackTs)> {
+    let (channel, ts) = key.split_once(':')?;
+    Some((SlackChannelId(channel.to_string()), SlackTs(ts.to_string())))
+}
+
+/// Stores the bot's data, such as entries and user settings, in the configured backend,
+/// see [`store::StorageBackend`]. Values are stored as JSON.
 #[derive(Clone, Debug)]
 pub struct Persistence {
-    redis: Arc<redis::Client>,
+    store: Arc<dyn Store>,
+    /// The backend in use, which differs from the configured one in degraded mode
+    backend: store::StorageBackend,
+    /// Prefix of all keys, so that the bot can share a redis instance with other apps
+    namespace: String,
 }
 
 impl Persistence {
+    /// Opens the configured store. If redis can't be reached and `ALLOW_DEGRADED_START`
+    /// is enabled, the in-memory store is used instead: Entries can still be recorded,
+    /// but settings such as default countries and cached data start out empty and are
+    /// lost on restart. Entries recorded meanwhile can be restored with `/woss backfill`.
+    /// Until then, the stats are read from the channel, see `slack::collect_entries`.
     pub async fn new(config: &AppConfig) -> Result<Self> {
-        let redis = Arc::new(redis::Client::open(config.redis_url.clone())?);
+        let (store, backend) = match store::open(config.storage_backend, &config.redis_url).await {
+            Ok(store) => (store, config.storage_backend),
+            Err(err)
+                if config.storage_backend == store::StorageBackend::Redis
+                    && config.allow_degraded_start =>
+            {
+                tracing::error!(
+                    "Redis is not reachable, starting in degraded mode. Data is only kept in \
+                     memory until the bot is restarted with redis available: {err:#}"
+                );
+                let backend = store::StorageBackend::Memory;
+                (store::open(backend, "").await?, backend)
+            }
+            Err(err) => return Err(err),
+        };
 
-        // Make sure that we can connect to the redis instance before proceeding
-        redis
-            .get_async_connection()
-            .await
-            .with_context(|| "Failed to start server, redis connection failed")?;
+        Ok(Persistence {
+            store,
+            backend,
+            namespace: config.redis_namespace.clone(),
+        })
+    }
 
-        Ok(Persistence { redis })
+    /// An empty in-memory store, for tests
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Persistence {
+            store: Arc::new(store::MemoryStore::default()),
+            backend: store::StorageBackend::Memory,
+            namespace: "test".to_string(),
+        }
     }
 
-    async fn get_redis_connection(&self) -> Result<redis::aio::Connection, AppError> {
-        self.redis.get_async_connection().await.map_err(|err| {
-            tracing::error!("Failed to acquire a redis connection: {err}");
-            err.into()
-        })
+    /// Whether the configured backend couldn't be opened, see [`Persistence::new`]
+    pub fn is_degraded(&self, config: &AppConfig) -> bool {
+        self.backend != config.storage_backend
+    }
+
+    /// Checks whether the store, e.g. the redis instance, is reachable
+    pub async fn ping(&self) -> Result<(), AppError> {
+        self.store.ping().await
+    }
+
+    fn namespaced(&self, key: &str) -> String {
+        format!("{}:{}", self.namespace, key)
+    }
+
+    /// Reads a value stored with [`Persistence::set`]. Returns `None` if the key doesn't
+    /// exist, redis can't be reached, or the value can't be deserialized.
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.get_raw(&self.namespaced(key)).await
+    }
+
+    /// Same as [`Persistence::get`], but falls back to the key that was used before
+    /// keys were namespaced. The legacy key is read as is, without the namespace. Only
+    /// use this for keys that were actually written before namespacing.
+    async fn get_migrated<T: DeserializeOwned>(&self, key: &str, legacy_key: &str) -> Option<T> {
+        match self.get(key).await {
+            Some(value) => Some(value),
+            None => self.get_raw(legacy_key).await,
+        }
+    }
+
+    async fn get_raw<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let raw = self.store.get(key).await.ok()??;
+
+        // Values stored before they were JSON encoded are plain strings
+        serde_json::from_str(&raw)
+            .or_else(|_| serde_json::from_value(serde_json::Value::String(raw)))
+            .map_err(|err| tracing::warn!("Failed to deserialize the value of {key}: {err}"))
+            .ok()
+    }
+
+    /// Stores the value as JSON. If a TTL is given, the key expires after that time.
+    pub async fn set<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Option<Duration>,
+    ) -> Result<(), AppError> {
+        let key = self.namespaced(key);
+        let raw = serde_json::to_string(value).map_err(anyhow::Error::from)?;
+        self.store.set(&key, &raw, ttl).await
     }
 
     pub async fn get_default_country(&self, user_id: SlackUserId) -> Option<String> {
-        let Some(mut conn) = self.get_redis_connection().await.ok() else {
-            return None;
-        };
-        conn.get::<_, String>(user_id.0.clone()).await.ok()
+        self.get_migrated(&format!("country:{}", user_id.0), &user_id.0)
+            .await
     }
 
     pub async fn set_default_country(
@@ -44,7 +154,618 @@ impl Persistence {
         user_id: SlackUserId,
         country: String,
     ) -> Result<(), AppError> {
-        let mut conn = self.get_redis_connection().await?;
-        conn.set(user_id.0, country).await.map_err(|err| err.into())
+        self.set(&format!("country:{}", user_id.0), &country, None)
+            .await
+    }
+
+    pub async fn get_team_token(&self, team_id: &SlackTeamId) -> Option<String> {
+        // Stored under the same key, but without the namespace, by older versions
+        let key = format!("token:{}", team_id.0);
+        self.get_migrated(&key, &key).await
+    }
+
+    pub async fn set_team_token(
+        &self,
+        team_id: &SlackTeamId,
+        token: String,
+    ) -> Result<(), AppError> {
+        self.set(&format!("token:{}", team_id.0), &token, None)
+            .await
+    }
+
+    /// Returns the ISO week (e.g. `2023-W04`) the weekly digest was last posted in
+    pub async fn get_digest_last_run(&self) -> Option<String> {
+        // Stored under the same key, but without the namespace, by older versions
+        self.get_migrated("digest:last_run", "digest:last_run")
+            .await
+    }
+
+    pub async fn set_digest_last_run(&self, week: String) -> Result<(), AppError> {
+        self.set("digest:last_run", &week, None).await
+    }
+
+    /// Returns the timestamp of the scoreboard message that the weekly digest updates
+    pub async fn get_scoreboard_ts(&self) -> Option<SlackTs> {
+        // Stored under the same key, but without the namespace, by older versions
+        self.get_migrated("digest:scoreboard_ts", "digest:scoreboard_ts")
+            .await
+    }
+
+    pub async fn set_scoreboard_ts(&self, ts: &SlackTs) -> Result<(), AppError> {
+        self.set("digest:scoreboard_ts", ts, None).await
+    }
+
+    /// Returns the timestamp of the leaderboard message pinned by `/woss pin`
+    pub async fn get_pinned_leaderboard_ts(&self) -> Option<SlackTs> {
+        self.get("leaderboard:pinned_ts").await
+    }
+
+    pub async fn set_pinned_leaderboard_ts(&self, ts: &SlackTs) -> Result<(), AppError> {
+        self.set("leaderboard:pinned_ts", ts, None).await
+    }
+
+    /// Returns the message that entries of the given project are posted as replies to,
+    /// see `FeatureFlags::project_threads`
+    pub async fn get_project_thread(&self, project: &str) -> Option<SlackTs> {
+        self.get(&format!("project_thread:{project}")).await
+    }
+
+    pub async fn set_project_thread(&self, project: &str, ts: &SlackTs) -> Result<(), AppError> {
+        self.set(&format!("project_thread:{project}"), ts, None)
+            .await
+    }
+
+    pub async fn get_leaderboard(&self) -> Option<Leaderboard> {
+        self.get("leaderboard").await
+    }
+
+    pub async fn set_leaderboard(&self, leaderboard: &Leaderboard) -> Result<(), AppError> {
+        self.set("leaderboard", leaderboard, None).await
+    }
+
+    pub async fn delete_leaderboard(&self) -> Result<(), AppError> {
+        self.delete("leaderboard").await
+    }
+
+    /// Returns the cached UTC offset of the user's timezone in seconds
+    pub async fn get_user_tz_offset(&self, user_id: &SlackUserId) -> Option<i32> {
+        self.get(&format!("tz_offset:{}", user_id.0)).await
+    }
+
+    pub async fn set_user_tz_offset(
+        &self,
+        user_id: &SlackUserId,
+        offset: i32,
+        ttl: Duration,
+    ) -> Result<(), AppError> {
+        self.set(&format!("tz_offset:{}", user_id.0), &offset, Some(ttl))
+            .await
+    }
+
+    /// Returns the cached Slack username of the user
+    pub async fn get_username(&self, user_id: &SlackUserId) -> Option<String> {
+        self.get(&format!("username:{}", user_id.0)).await
+    }
+
+    pub async fn set_username(
+        &self,
+        user_id: &SlackUserId,
+        username: &str,
+        ttl: Duration,
+    ) -> Result<(), AppError> {
+        self.set(&format!("username:{}", user_id.0), &username, Some(ttl))
+            .await
+    }
+
+    /// Returns the number of hours the user aims to contribute per quarter
+    pub async fn get_goal(&self, user_id: &SlackUserId) -> Option<f64> {
+        self.get(&format!("goal:{}", user_id.0)).await
+    }
+
+    /// Sets the user's goal, or removes it if `None` is given
+    pub async fn set_goal(
+        &self,
+        user_id: &SlackUserId,
+        hours: Option<f64>,
+    ) -> Result<(), AppError> {
+        let key = format!("goal:{}", user_id.0);
+        match hours {
+            Some(hours) => self.set(&key, &hours, None).await,
+            None => self.delete(&key).await,
+        }
+    }
+
+    /// Stores an entry, so that it is still available once Slack deleted the message it
+    /// was posted as, e.g. due to the workspace's retention policy. The entry is keyed
+    /// by that message, so storing it again (e.g. after editing) replaces it.
+    pub async fn store_entry(
+        &self,
+        channel: &SlackChannelId,
+        ts: &SlackTs,
+        entry: &OpenSourceAttachment,
+    ) -> Result<(), AppError> {
+        let raw = serde_json::to_string(entry).map_err(anyhow::Error::from)?;
+        let key = entry_key(channel, ts);
+        self.store
+            .hash_set(&self.namespaced("entries"), &key, &raw)
+            .await?;
+        self.index_entry(&key, entry).await
+    }
+
+    /// Same as [`Persistence::store_entry`], but keeps the stored entry if there is one
+    /// already, which may have been edited since. Returns whether the entry was stored.
+    pub async fn store_entry_if_missing(
+        &self,
+        channel: &SlackChannelId,
+        ts: &SlackTs,
+        entry: &OpenSourceAttachment,
+    ) -> Result<bool, AppError> {
+        let raw = serde_json::to_string(entry).map_err(anyhow::Error::from)?;
+        let key = entry_key(channel, ts);
+        let stored = self
+            .store
+            .hash_set_if_missing(&self.namespaced("entries"), &key, &raw)
+            .await?;
+        if stored {
+            self.index_entry(&key, entry).await?;
+        }
+        Ok(stored)
+    }
+
+    /// Adds the entry to the index by submission date, which lets
+    /// [`Persistence::get_entries`] read only the entries of the requested range.
+    /// Entries without a submission date are never part of a range, so they aren't
+    /// indexed.
+    async fn index_entry(&self, key: &str, entry: &OpenSourceAttachment) -> Result<(), AppError> {
+        match entry.submitted_at {
+            Some(date) => {
+                self.store
+                    .sorted_set_add(
+                        &self.namespaced("entries_by_date"),
+                        key,
+                        date.timestamp_millis(),
+                    )
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Indexes the entries that were stored before the index by submission date
+    /// existed. This is only done once, entries stored since are indexed right away.
+    async fn ensure_entries_indexed(&self) -> Result<(), AppError> {
+        let indexed_key = self.namespaced("entries_by_date_complete");
+        if self.store.get(&indexed_key).await?.is_some() {
+            return Ok(());
+        }
+
+        tracing::info!("Indexing the stored entries by submission date");
+        for (key, raw) in self.store.hash_get_all(&self.namespaced("entries")).await? {
+            match serde_json::from_str::<OpenSourceAttachment>(&raw) {
+                Ok(entry) => self.index_entry(&key, &entry).await?,
+                Err(err) => tracing::warn!("Failed to deserialize a stored entry: {err}"),
+            }
+        }
+        self.store.set(&indexed_key, "true", None).await
+    }
+
+    /// Marks the entry posted as the given message as ignored, which excludes it from
+    /// [`Persistence::get_entries`], or includes it again
+    pub async fn set_entry_ignored(
+        &self,
+        channel: &SlackChannelId,
+        ts: &SlackTs,
+        ignored: bool,
+    ) -> Result<(), AppError> {
+        let key = self.namespaced("ignored_entries");
+        if ignored {
+            self.store.set_add(&key, &entry_key(channel, ts)).await
+        } else {
+            self.store.set_remove(&key, &entry_key(channel, ts)).await
+        }
+    }
+
+    /// Returns the stored entries that aren't ignored, newest first. If dates are given,
+    /// only entries submitted in between are returned, both ends inclusive. Only those
+    /// entries are read then, so that the stats of a short range stay cheap as the
+    /// number of stored entries grows.
+    pub async fn get_entries(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<OpenSourceAttachment>, AppError> {
+        if since.is_none() && until.is_none() {
+            let entries = self.get_entries_by_message().await?;
+            return Ok(entries.into_iter().map(|(_, _, entry)| entry).collect());
+        }
+
+        self.ensure_entries_indexed().await?;
+        let ignored = self
+            .store
+            .set_members(&self.namespaced("ignored_entries"))
+            .await?;
+        let keys: Vec<String> = self
+            .store
+            .sorted_set_range(
+                &self.namespaced("entries_by_date"),
+                since.map(|date| date.timestamp_millis()),
+                until.map(|date| date.timestamp_millis()),
+            )
+            .await?
+            .into_iter()
+            .filter(|key| !ignored.contains(key))
+            .collect();
+        let values = self
+            .store
+            .hash_get_many(&self.namespaced("entries"), &keys)
+            .await?;
+
+        let mut entries: Vec<OpenSourceAttachment> = values
+            .into_iter()
+            .flatten()
+            .filter_map(|raw| {
+                serde_json::from_str(&raw)
+                    .map_err(|err| tracing::warn!("Failed to deserialize a stored entry: {err}"))
+                    .ok()
+            })
+            .collect();
+        entries.sort_by(|a, b| b.submitted_at.cmp(&a.submitted_at));
+
+        Ok(entries)
+    }
+
+    /// Same as [`Persistence::get_entries`], along with the message each entry was
+    /// posted as, so that the message can be updated
+    pub async fn get_entries_by_message(
+        &self,
+    ) -> Result<Vec<(SlackChannelId, SlackTs, OpenSourceAttachment)>, AppError> {
+        let values = self.store.hash_get_all(&self.namespaced("entries")).await?;
+        let ignored = self
+            .store
+            .set_members(&self.namespaced("ignored_entries"))
+            .await?;
+
+        let mut entries: Vec<(SlackChannelId, SlackTs, OpenSourceAttachment)> = values
+            .iter()
+            .filter(|(key, _)| !ignored.contains(key))
+            .filter_map(|(key, raw)| {
+                let (channel, ts) = parse_entry_key(key)?;
+                let entry = serde_json::from_str(raw)
+                    .map_err(|err| tracing::warn!("Failed to deserialize a stored entry: {err}"))
+                    .ok()?;
+                Some((channel, ts, entry))
+            })
+            .collect();
+        entries.sort_by(|(_, _, a), (_, _, b)| b.submitted_at.cmp(&a.submitted_at));
+
+        Ok(entries)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.store.delete(&self.namespaced(key)).await
+    }
+
+    /// Deletes the key if it exists, without namespacing it. Returns whether it existed.
+    async fn delete_if_present(&self, key: &str) -> Result<bool, AppError> {
+        if self.store.get(key).await?.is_none() {
+            return Ok(false);
+        }
+        self.store.delete(key).await?;
+        Ok(true)
+    }
+
+    /// Deletes everything stored about the user: their settings, cached information and
+    /// stored entries. Entries are stored with the username rather than the user ID, so
+    /// it has to be given to delete them. The user is removed from the co-authors of
+    /// other entries as well. The messages the entries were posted as are kept.
+    pub async fn delete_user_data(
+        &self,
+        user_id: &SlackUserId,
+        username: Option<&str>,
+    ) -> Result<DeletedUserData, AppError> {
+        let mut deleted = DeletedUserData::default();
+
+        let settings = [
+            (
+                "default country",
+                self.namespaced(&format!("country:{}", user_id.0)),
+            ),
+            // Stored before keys were namespaced, see `get_default_country`
+            ("default country", user_id.0.clone()),
+            ("goal", self.namespaced(&format!("goal:{}", user_id.0))),
+            (
+                "timezone",
+                self.namespaced(&format!("tz_offset:{}", user_id.0)),
+            ),
+            (
+                "username",
+                self.namespaced(&format!("username:{}", user_id.0)),
+            ),
+            (
+                "rate limit",
+                self.namespaced(&format!("rate_limit:record_hours:{}", user_id.0)),
+            ),
+        ];
+        for (name, key) in settings {
+            if self.delete_if_present(&key).await? && !deleted.settings.contains(&name) {
+                deleted.settings.push(name);
+            }
+        }
+
+        let Some(username) = username else {
+            return Ok(deleted);
+        };
+
+        let entries_key = self.namespaced("entries");
+        let ignored_key = self.namespaced("ignored_entries");
+        let by_date_key = self.namespaced("entries_by_date");
+        for (key, raw) in self.store.hash_get_all(&entries_key).await? {
+            let Ok(mut entry) = serde_json::from_str::<OpenSourceAttachment>(&raw) else {
+                continue;
+            };
+
+            if entry.username == username {
+                self.store.hash_delete(&entries_key, &key).await?;
+                self.store.set_remove(&ignored_key, &key).await?;
+                self.store.sorted_set_remove(&by_date_key, &key).await?;
+                deleted.entries += 1;
+            } else if entry.co_authors.iter().any(|name| name == username) {
+                entry.co_authors.retain(|name| name != username);
+                let raw = serde_json::to_string(&entry).map_err(anyhow::Error::from)?;
+                self.store.hash_set(&entries_key, &key, &raw).await?;
+                deleted.co_authored_entries += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Takes a token from the user's bucket for the given action. The bucket holds up to
+    /// `capacity` tokens and is refilled at that many tokens per `period`. Returns
+    /// `false` if the bucket is empty, i.e. the user should be throttled.
+    ///
+    /// Reading and writing the bucket isn't atomic, so concurrent requests of the same
+    /// user may both get a token. That's good enough to prevent spamming.
+    pub async fn take_rate_limit_token(
+        &self,
+        action: &str,
+        user_id: &SlackUserId,
+        capacity: u32,
+        period: Duration,
+    ) -> Result<bool, AppError> {
+        let key = format!("rate_limit:{action}:{}", user_id.0);
+        let now = Utc::now();
+        let capacity = f64::from(capacity);
+
+        let tokens = match self.get::<TokenBucket>(&key).await {
+            Some(bucket) => {
+                let elapsed = (now - bucket.updated_at).num_milliseconds().max(0) as f64;
+                let refilled = elapsed / period.as_millis() as f64 * capacity;
+                (bucket.tokens + refilled).min(capacity)
+            }
+            None => capacity,
+        };
+
+        if tokens < 1.0 {
+            return Ok(false);
+        }
+
+        let bucket = TokenBucket {
+            tokens: tokens - 1.0,
+            updated_at: now,
+        };
+        // A full bucket is the same as no bucket, so it can expire once refilled
+        self.set(&key, &bucket, Some(period)).await?;
+
+        Ok(true)
+    }
+
+    /// Tries to acquire the lock with the given name, e.g. so that only one of several
+    /// instances of the bot runs a task. The lock expires after the given time, so that
+    /// an instance that crashed doesn't hold it forever. Returns the token needed to
+    /// release the lock, or `None` if another instance holds it.
+    pub async fn acquire_lock(
+        &self,
+        name: &str,
+        ttl: Duration,
+    ) -> Result<Option<String>, AppError> {
+        let token = format!("{:016x}", rand::random::<u64>());
+        let acquired = self
+            .store
+            .set_if_missing(&self.namespaced(&format!("lock:{name}")), &token, ttl)
+            .await?;
+
+        Ok(acquired.then_some(token))
+    }
+
+    /// Releases a lock acquired with [`Persistence::acquire_lock`]. If the lock expired
+    /// and was acquired by another instance in the meantime, it is left alone.
+    pub async fn release_lock(&self, name: &str, token: &str) -> Result<(), AppError> {
+        self.store
+            .delete_if_equal(&self.namespaced(&format!("lock:{name}")), token)
+            .await
+    }
+
+    /// Records that a submission with the given fingerprint was seen. Returns `false`
+    /// if the same fingerprint was already recorded within the given time window.
+    pub async fn record_submission(
+        &self,
+        fingerprint: &str,
+        window: Duration,
+    ) -> Result<bool, AppError> {
+        self.store
+            .set_if_missing(
+                &self.namespaced(&format!("submission:{fingerprint}")),
+                "1",
+                window,
+            )
+            .await
+    }
+
+    /// Forgets a fingerprint recorded with [`Persistence::record_submission`], e.g. when
+    /// the submission couldn't be posted, so that the user can submit it again
+    pub async fn forget_submission(&self, fingerprint: &str) -> Result<(), AppError> {
+        self.delete(&format!("submission:{fingerprint}")).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn lock_is_exclusive_until_released() {
+        let persistence = Persistence::in_memory();
+
+        let token = persistence.acquire_lock("digest", TTL).await.unwrap();
+        let token = token.expect("The lock is free");
+        assert_eq!(persistence.acquire_lock("digest", TTL).await.unwrap(), None);
+        // Other locks are independent
+        assert!(persistence
+            .acquire_lock("leaderboard", TTL)
+            .await
+            .unwrap()
+            .is_some());
+
+        persistence
+            .release_lock("digest", "other-token")
+            .await
+            .unwrap();
+        assert_eq!(persistence.acquire_lock("digest", TTL).await.unwrap(), None);
+
+        persistence.release_lock("digest", &token).await.unwrap();
+        assert!(persistence
+            .acquire_lock("digest", TTL)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn stored_entries_are_read_back() {
+        let persistence = Persistence::in_memory();
+        let channel = SlackChannelId("COSS".to_string());
+        let entry = |hours: f64, day: u32| OpenSourceAttachment {
+            username: "jane".to_string(),
+            number_of_hours: hours,
+            country: "germany".to_string(),
+            url: "https://github.com/x3ro/wizard-of-oss/pull/1"
+                .parse()
+                .unwrap(),
+            project: None,
+            description: "Fixed the build".to_string(),
+            tags: vec![],
+            co_authors: vec![],
+            submitted_at: Some(format!("2023-01-{day:02}T10:00:00Z").parse().unwrap()),
+        };
+        let older = entry(1.0, 1);
+        let newer = entry(2.5, 2);
+
+        let ts = |ts: &str| SlackTs(ts.to_string());
+        persistence
+            .store_entry(&channel, &ts("1672567200.000000"), &older)
+            .await
+            .unwrap();
+        persistence
+            .store_entry(&channel, &ts("1672653600.000000"), &newer)
+            .await
+            .unwrap();
+
+        // Newest first
+        let entries = persistence.get_entries(None, None).await.unwrap();
+        assert_eq!(entries, [newer.clone(), older.clone()]);
+
+        let since = "2023-01-02T00:00:00Z".parse().unwrap();
+        let until = "2023-01-01T23:59:59Z".parse().unwrap();
+        assert_eq!(
+            persistence.get_entries(Some(since), None).await.unwrap(),
+            [newer]
+        );
+        assert_eq!(
+            persistence.get_entries(None, Some(until)).await.unwrap(),
+            [older]
+        );
+    }
+
+    #[tokio::test]
+    async fn entries_stored_before_the_index_are_read_by_range() {
+        let persistence = Persistence::in_memory();
+        let channel = SlackChannelId("COSS".to_string());
+        let entry = OpenSourceAttachment {
+            username: "jane".to_string(),
+            number_of_hours: 1.0,
+            country: "germany".to_string(),
+            url: "https://github.com/x3ro/wizard-of-oss/pull/1"
+                .parse()
+                .unwrap(),
+            project: None,
+            description: "Fixed the build".to_string(),
+            tags: vec![],
+            co_authors: vec![],
+            submitted_at: Some("2023-01-01T10:00:00Z".parse().unwrap()),
+        };
+        // Stored without being indexed, like entries stored by earlier versions
+        let raw = serde_json::to_string(&entry).unwrap();
+        let key = entry_key(&channel, &SlackTs("1672567200.000000".to_string()));
+        persistence
+            .store
+            .hash_set(&persistence.namespaced("entries"), &key, &raw)
+            .await
+            .unwrap();
+        let ignored = SlackTs("1672567300.000000".to_string());
+        persistence
+            .store_entry(&channel, &ignored, &entry)
+            .await
+            .unwrap();
+        persistence
+            .set_entry_ignored(&channel, &ignored, true)
+            .await
+            .unwrap();
+
+        let since = "2023-01-01T00:00:00Z".parse().unwrap();
+        let entries = persistence.get_entries(Some(since), None).await.unwrap();
+
+        assert_eq!(entries, [entry]);
+    }
+
+    #[tokio::test]
+    async fn values_stored_before_namespacing_are_read() {
+        let persistence = Persistence::in_memory();
+        let team_id = SlackTeamId("T0000000000".to_string());
+        let user_id = SlackUserId("U0000000001".to_string());
+        persistence
+            .store
+            .set("token:T0000000000", "xoxb-legacy", None)
+            .await
+            .unwrap();
+        persistence
+            .store
+            .set("U0000000001", "germany", None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            persistence.get_team_token(&team_id).await.as_deref(),
+            Some("xoxb-legacy")
+        );
+        assert_eq!(
+            persistence
+                .get_default_country(user_id.clone())
+                .await
+                .as_deref(),
+            Some("germany")
+        );
+
+        // Values stored since then take precedence
+        persistence
+            .set_default_country(user_id.clone(), "france".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            persistence.get_default_country(user_id).await.as_deref(),
+            Some("france")
+        );
     }
 }
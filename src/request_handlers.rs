@@ -1,35 +1,58 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, Context};
+use anyhow::anyhow;
+use axum::response::IntoResponse;
 use axum::{Extension, Json};
+use chrono::{NaiveDate, TimeZone, Utc};
 use hyper::{Body, Response};
-use lazy_static::lazy_static;
-use rand::prelude::SliceRandom;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use slack_morphism::errors::SlackClientError;
 use slack_morphism::prelude::*;
+use slack_morphism::signature_verifier::{
+    SlackEventAbsentSignatureError, SlackEventSignatureVerifierError,
+};
 use tracing::*;
-use url::Url;
+use url::{Host, Url};
 
-use crate::errors::AppError;
-use crate::models::OpenSourceAttachment;
+use crate::errors::{self, AppError};
+use crate::models::{self, OpenSourceAttachment};
+use crate::persistence::Persistence;
 use crate::slack::SlackViewStateExt;
-use crate::{slack, AppConfig, AppState};
-
-const RAW_LOADING_MESSAGES: &str = include_str!("../loading-messages.txt");
-lazy_static! {
-    static ref LOADING_MESSAGES: Vec<&'static str> = RAW_LOADING_MESSAGES.split('\n').collect();
-}
+use crate::{
+    countries, loading_messages, message_template, slack, webhook, AppConfig, AppState,
+    PostIdentity,
+};
 
 // --------
 // Handlers
 // --------
 
-pub async fn test_oauth_install_function(
+/// Called after the app was installed into a workspace. The token is stored per team,
+/// so that events coming from that workspace can be handled with the right token.
+pub async fn oauth_install_function(
     resp: SlackOAuthV2AccessTokenResponse,
     _client: Arc<SlackHyperClient>,
-    _states: SlackClientEventsUserState,
+    states: SlackClientEventsUserState,
 ) {
-    println!("HELLO AUTH {:#?}", resp);
-    println!("token {}", resp.access_token.0);
+    let team_id = resp.team.id;
+    info!("App was installed into team {}", team_id.0);
+
+    let states = states.read().await;
+    let Some(persistence) = states.get_user_state::<Persistence>() else {
+        error!("Persistence is missing from the listener state, can't store the token");
+        return;
+    };
+
+    if let Err(err) = persistence
+        .set_team_token(&team_id, resp.access_token.0)
+        .await
+    {
+        error!("Failed to store the token for team {}: {err:?}", team_id.0);
+    }
 }
 
 // The install handlers aren't really needed if one wants
@@ -52,64 +75,1285 @@ pub async fn install_error_handler() -> String {
     "Error while installing".to_string()
 }
 
+// Liveness and readiness probes, e.g. for Kubernetes. The service is
+// only considered ready if it can reach redis.
+
+pub async fn health_handler() -> http::StatusCode {
+    http::StatusCode::OK
+}
+
+pub async fn readiness_handler(Extension(state): Extension<AppState>) -> http::StatusCode {
+    match state.persistence.ping().await {
+        Ok(()) => http::StatusCode::OK,
+        Err(_) => {
+            warn!("Readiness check failed, redis is not reachable");
+            http::StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+/// Recomputes the leaderboard, so that external schedulers such as a cron job can keep
+/// it up to date. Requests must contain `ADMIN_API_SECRET` in the `X-Admin-Secret`
+/// header. Without a configured secret, the route responds as if it didn't exist.
+pub async fn admin_recompute_handler(
+    headers: http::HeaderMap,
+    Extension(state): Extension<AppState>,
+    Extension(config): Extension<AppConfig>,
+) -> Result<Response<axum::body::BoxBody>, AppError> {
+    let Some(secret) = &config.admin_api_secret else {
+        return Ok(http::StatusCode::NOT_FOUND.into_response());
+    };
+
+    let provided = headers
+        .get("x-admin-secret")
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+    if !constant_time_eq(provided, secret.as_bytes()) {
+        warn!("Rejecting admin request with a missing or wrong secret");
+        return Ok(http::StatusCode::UNAUTHORIZED.into_response());
+    }
+
+    let started_at = Instant::now();
+    let leaderboard = slack::recompute_leaderboard(&state, &config).await?;
+    let duration = started_at.elapsed();
+    info!("Recomputed the leaderboard via the admin API in {duration:?}");
+
+    Ok(Json(json!({
+        "contributors": leaderboard.hours_by_user.len(),
+        "unique_contributors": leaderboard
+            .unique_hours_by_user
+            .as_ref()
+            .map_or(0, Vec::len),
+        "computed_at": leaderboard.computed_at,
+        "duration_ms": duration.as_millis() as u64,
+    }))
+    .into_response())
+}
+
+/// Compares secrets without returning early, so that the time taken doesn't reveal how
+/// many leading bytes of a guess were right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 // -------------------------------------
 // Here the important handlers begin vvv
 // -------------------------------------
 
-pub async fn push_event_handler(Extension(event): Extension<SlackPushEvent>) -> Response<Body> {
+pub async fn push_event_handler(
+    Extension(event): Extension<SlackPushEvent>,
+    Extension(state): Extension<AppState>,
+    Extension(config): Extension<AppConfig>,
+) -> Response<Body> {
     trace!("Received push event: {:?}", event);
 
     match event {
         SlackPushEvent::UrlVerification(url_ver) => Response::new(Body::from(url_ver.challenge)),
+
+        SlackPushEvent::EventCallback(SlackPushEventCallback {
+            team_id,
+            event: SlackEventCallbackBody::AppHomeOpened(home),
+            ..
+        }) if home.tab.as_deref() == Some("home") => {
+            tokio::spawn(
+                async move {
+                    let state = state.for_team(&team_id).await;
+                    if let Err(err) = slack::publish_home_view(&state, &config, home.user).await {
+                        error!("Failed to publish the home view: {err:?}");
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Response::new(Body::empty())
+        }
+
+        SlackPushEvent::EventCallback(SlackPushEventCallback {
+            event: SlackEventCallbackBody::ReactionAdded(reaction),
+            ..
+        }) => {
+            spawn_reaction_handler(
+                state,
+                config,
+                reaction.user,
+                reaction.reaction,
+                reaction.item,
+                true,
+            );
+            Response::new(Body::empty())
+        }
+
+        SlackPushEvent::EventCallback(SlackPushEventCallback {
+            event: SlackEventCallbackBody::ReactionRemoved(reaction),
+            ..
+        }) => {
+            spawn_reaction_handler(
+                state,
+                config,
+                reaction.user,
+                reaction.reaction,
+                reaction.item,
+                false,
+            );
+            Response::new(Body::empty())
+        }
+
         _ => Response::new(Body::empty()),
     }
 }
 
+fn spawn_reaction_handler(
+    state: AppState,
+    config: AppConfig,
+    user: SlackUserId,
+    reaction: SlackReactionName,
+    item: SlackReactionsItem,
+    added: bool,
+) {
+    let SlackReactionsItem::Message(message) = item else {
+        return;
+    };
+
+    tokio::spawn(
+        async move {
+            let res = handle_reaction(&state, &config, &user, &reaction, &message, added).await;
+            if let Err(err) = res {
+                error!("Failed to handle a reaction: {err:?}");
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+/// Admins can exclude an entry from the stats by reacting with the configured emoji,
+/// e.g. if it's obviously wrong but the message can't be deleted. Removing the reaction
+/// includes the entry again. Reactions of other users are ignored.
+///
+/// Reacting with the quick log emoji offers to log a message's link instead, see
+/// [`handle_quick_log_reaction`].
+async fn handle_reaction(
+    state: &AppState,
+    config: &AppConfig,
+    user: &SlackUserId,
+    reaction: &SlackReactionName,
+    message: &SlackHistoryMessage,
+    added: bool,
+) -> Result<(), AppError> {
+    if added && config.quick_log_reaction.as_ref() == Some(&reaction.0) {
+        return handle_quick_log_reaction(state, user, message).await;
+    }
+
+    let oss_channel = SlackChannelId(config.slack_oss_channel_id.clone());
+    if reaction.0 != config.ignore_reaction
+        || message.origin.channel.as_ref() != Some(&oss_channel)
+        || !config.is_admin(user)
+    {
+        return Ok(());
+    }
+
+    info!(
+        "{} {} the entry posted at {}",
+        user.0,
+        if added { "ignored" } else { "restored" },
+        message.origin.ts.0
+    );
+    state
+        .persistence
+        .set_entry_ignored(&oss_channel, &message.origin.ts, added)
+        .await?;
+    state.entries_cache.invalidate();
+
+    Ok(())
+}
+
+/// Offers to log the link in the message that was reacted to with the configured emoji,
+/// see `QUICK_LOG_REACTION`. Slack doesn't provide a trigger ID for reactions, which is
+/// needed to open a modal, so the user gets a button that opens it instead.
+async fn handle_quick_log_reaction(
+    state: &AppState,
+    user: &SlackUserId,
+    message: &SlackHistoryMessage,
+) -> Result<(), AppError> {
+    let Some(channel) = message.origin.channel.clone() else {
+        return Ok(());
+    };
+
+    let req = SlackApiUsersInfoRequest {
+        user: user.clone(),
+        include_locale: None,
+    };
+    let res = state
+        .slack
+        .users_info(&req)
+        .await
+        .map_err(AppError::slack_api("users.info"))?;
+    if res.user.flags.is_bot == Some(true) {
+        return Ok(());
+    }
+
+    // The event only identifies the message, its text has to be read separately
+    let url = slack::fetch_message(state, &channel, &message.origin.ts)
+        .await?
+        .and_then(|message| message.content.text)
+        .and_then(|text| models::find_slack_url(&text));
+    let Some(url) = url else {
+        let text = "There's no link in this message that could be logged.";
+        return post_notice(state, channel, user.clone(), text).await;
+    };
+
+    let text = format!("Do you want to log your contribution {url}?");
+    let blocks: Vec<SlackBlock> = serde_json::from_value(json!([
+        {
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": text },
+        },
+        {
+            "type": "actions",
+            "elements": [{
+                "type": "button",
+                "action_id": "quick_log",
+                "value": url.to_string(),
+                "style": "primary",
+                "text": { "type": "plain_text", "text": "Record hours" },
+            }],
+        },
+    ]))
+    .map_err(anyhow::Error::from)?;
+
+    let req = SlackApiChatPostEphemeralRequest {
+        channel,
+        user: user.clone(),
+        content: SlackMessageContent::new()
+            .with_text(text)
+            .with_blocks(blocks),
+        as_user: None,
+        icon_emoji: None,
+        icon_url: None,
+        link_names: None,
+        parse: None,
+        thread_ts: None,
+        username: None,
+    };
+    state
+        .slack
+        .chat_post_ephemeral(&req)
+        .await
+        .map_err(AppError::slack_api("chat.postEphemeral"))?;
+
+    Ok(())
+}
+
 pub async fn command_event_handler(
     Extension(event): Extension<SlackCommandEvent>,
     Extension(state): Extension<AppState>,
     Extension(config): Extension<AppConfig>,
 ) -> Result<Json<SlackCommandEventResponse>, AppError> {
     trace!("Received command event: {:?}", event);
+    record_user(&event.user_id);
+    let command = format!(
+        "{} {}",
+        event.command.0,
+        event.text.as_deref().unwrap_or_default()
+    );
+    Span::current().record("command", command.trim());
+    let state = state.for_team(&event.team_id).await;
 
     if event.command.as_ref() != "/woss" {
         return Err(anyhow!("Unknown command {}", event.command.as_ref()).into());
     }
 
-    match event.text.as_deref() {
-        Some("stats") => {
-            tokio::spawn(async move { slack::report_user_stats(&state, &config, &event).await });
+    // Built upfront, since the config is moved into the tasks spawned below
+    let loading_response = loading_message(&config);
+
+    let text = event.text.as_deref().unwrap_or_default();
+    match Subcommand::parse(text, &config.command_aliases) {
+        Subcommand::Stats {
+            tag,
+            user,
+            verbose,
+            since,
+            until,
+        } => {
+            let range = match parse_entry_range(since.as_deref(), until.as_deref()) {
+                Ok(range) => range,
+                Err(text) => return Ok(Json(ephemeral_response(text))),
+            };
+
+            tokio::spawn(
+                async move {
+                    slack::report_user_stats(&state, &config, &event, tag, user, range, verbose)
+                        .await
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Recent { count } => {
+            let count = count
+                .unwrap_or(slack::DEFAULT_RECENT_ENTRIES)
+                .clamp(1, slack::MAX_RECENT_ENTRIES);
+            tokio::spawn(
+                async move { slack::report_recent(&state, &config, &event, count).await }
+                    .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::TeamStats { tag } => {
+            tokio::spawn(
+                async move { slack::report_team_stats(&state, &config, &event, tag).await }
+                    .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Trend { tag } => {
+            if !config.features.trend {
+                let text = "Trends aren't enabled in this workspace.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+
+            tokio::spawn(
+                async move { slack::report_trend(&state, &config, &event, tag).await }
+                    .in_current_span(),
+            );
 
-            Ok(Json(loading_message()))
+            Ok(Json(loading_response))
         }
 
-        Some(_params) => {
+        Subcommand::Export => {
+            if !config.is_admin(&event.user_id) {
+                let text = "Only admins can export all entries.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+
+            tokio::spawn(
+                async move {
+                    if let Err(err) = slack::export_entries(&state, &config, &event).await {
+                        error!("Failed to export entries: {err:?}");
+                        let text = "Failed to export the entries.";
+                        let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+                        if let Err(err) = post_notice(&state, channel, event.user_id, text).await {
+                            error!("Failed to report the failed export: {err:?}");
+                        }
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::ExportJson { user } => {
+            let user_id = user.unwrap_or_else(|| event.user_id.clone());
+            if user_id != event.user_id && !config.is_admin(&event.user_id) {
+                let text = "Only admins can export the entries of other users.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+
+            tokio::spawn(
+                async move {
+                    if let Err(err) =
+                        slack::export_user_entries(&state, &config, &event, &user_id).await
+                    {
+                        error!("Failed to export the entries of {}: {err:?}", user_id.0);
+                        let text = "Failed to export the entries.";
+                        let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+                        if let Err(err) = post_notice(&state, channel, event.user_id, text).await {
+                            error!("Failed to report the failed export: {err:?}");
+                        }
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Report { month } => {
+            let parsed = month.as_deref().and_then(|month| {
+                NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok()
+            });
+            let Some(month) = parsed else {
+                let text = "Please enter the month of the report as YYYY-MM, e.g. \
+                            `/woss report 2023-01`.";
+                return Ok(Json(ephemeral_response(text.into())));
+            };
+
+            tokio::spawn(
+                async move {
+                    if let Err(err) =
+                        slack::send_monthly_report(&state, &config, &event, month).await
+                    {
+                        error!("Failed to send the monthly report: {err:?}");
+                        let text = "Failed to create the monthly report.";
+                        let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+                        if let Err(err) = post_notice(&state, channel, event.user_id, text).await {
+                            error!("Failed to report the failed monthly report: {err:?}");
+                        }
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Office { country } => {
+            let text = match countries::find(&country) {
+                Some(selected) => {
+                    state
+                        .persistence
+                        .set_default_country(event.user_id, selected.value.clone())
+                        .await?;
+                    format!("Your office is now preselected as {}.", selected.label)
+                }
+                None => unknown_office_message(&country),
+            };
+
+            Ok(Json(ephemeral_response(text)))
+        }
+
+        Subcommand::Version => {
+            let text = format!(
+                "*Version:* {} (commit `{}`)\n*Uptime:* {}",
+                env!("CARGO_PKG_VERSION"),
+                env!("GIT_COMMIT"),
+                format_duration(state.started_at.elapsed())
+            );
+            Ok(Json(ephemeral_response(text)))
+        }
+
+        Subcommand::Status => {
+            if !config.is_admin(&event.user_id) {
+                let text = "Only admins can see the status of the bot.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+
+            let text = status_report(&state, &config, &event.command).await;
+            Ok(Json(ephemeral_response(text)))
+        }
+
+        Subcommand::Me { verbose } => {
+            let user = Some(event.user_id.clone());
+            tokio::spawn(
+                async move {
+                    let range = slack::EntryRange::default();
+                    slack::report_user_stats(&state, &config, &event, None, user, range, verbose)
+                        .await
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Goal { hours } => {
+            let text = match hours.as_deref() {
+                None => match state.persistence.get_goal(&event.user_id).await {
+                    Some(goal) => format!(
+                        "Your goal is {} hours per quarter, see `/woss me` for your \
+                         progress. Use `/woss goal off` to remove it.",
+                        models::display_hours(goal)
+                    ),
+                    None => "You haven't set a goal yet, e.g. `/woss goal 20` for 20 hours \
+                             per quarter."
+                        .to_string(),
+                },
+                Some("off") => {
+                    state.persistence.set_goal(&event.user_id, None).await?;
+                    "Your goal was removed.".to_string()
+                }
+                Some(input) => match models::parse_hours(input) {
+                    Some(goal) if goal > 0.0 => {
+                        state
+                            .persistence
+                            .set_goal(&event.user_id, Some(goal))
+                            .await?;
+                        format!(
+                            "Your goal is now {} hours per quarter, see `/woss me` for \
+                             your progress.",
+                            models::display_hours(goal)
+                        )
+                    }
+                    _ => format!(
+                        "Invalid goal '{input}', please enter a number of hours per quarter, \
+                         e.g. `/woss goal 20`."
+                    ),
+                },
+            };
+
+            Ok(Json(ephemeral_response(text)))
+        }
+
+        Subcommand::Leaderboard { unique, office } => {
+            let office = match office {
+                Some(office) => match countries::find(&office) {
+                    Some(office) => Some(office),
+                    None => return Ok(Json(ephemeral_response(unknown_office_message(&office)))),
+                },
+                None => None,
+            };
+
+            tokio::spawn(
+                async move {
+                    slack::report_leaderboard(&state, &config, &event, unique, office).await
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Fix { url, hours } => {
+            let (Some(url), Some(hours)) = (url, hours) else {
+                let text = "Please enter the URL of the entry and the correct number of hours, \
+                            e.g. `/woss fix https://github.com/x3ro/wizard-of-oss/pull/1 4`.";
+                return Ok(Json(ephemeral_response(text.into())));
+            };
+            let Ok(url) = models::parse_slack_url(&url) else {
+                return Ok(Json(ephemeral_response(format!("Invalid URL '{url}'."))));
+            };
+            let hours = match validate_hours(&hours, &config) {
+                Ok(hours) => hours,
+                Err(AppError::InputValidationError { message, .. }) => {
+                    return Ok(Json(ephemeral_response(format!("{message}."))));
+                }
+                Err(err) => return Err(err),
+            };
+
+            tokio::spawn(
+                async move {
+                    let text =
+                        match fix_entry_hours(&state, &config, &event.user_id, &url, hours).await {
+                            Ok(text) => text,
+                            Err(err) => {
+                                error!("Failed to fix the hours of an entry: {err:?}");
+                                "Failed to change the hours of the entry.".to_string()
+                            }
+                        };
+                    let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+                    if let Err(err) = post_notice(&state, channel, event.user_id, &text).await {
+                        error!("Failed to report the fixed entry: {err:?}");
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Recompute => {
+            if !config.is_admin(&event.user_id) {
+                let text = "Only admins can recompute the leaderboard.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+
+            tokio::spawn(
+                async move {
+                    let text = match slack::recompute_leaderboard(&state, &config).await {
+                        Ok(leaderboard) => format!(
+                            "Recomputed the leaderboard with {} contributors.",
+                            leaderboard.hours_by_user.len()
+                        ),
+                        Err(err) => {
+                            error!("Failed to recompute the leaderboard: {err:?}");
+                            "Failed to recompute the leaderboard.".to_string()
+                        }
+                    };
+                    let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+                    if let Err(err) = post_notice(&state, channel, event.user_id, &text).await {
+                        error!("Failed to report the recomputed leaderboard: {err:?}");
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Pin => {
+            if !config.is_admin(&event.user_id) {
+                let text = "Only admins can pin the leaderboard.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+
+            tokio::spawn(
+                async move {
+                    let text = match slack::pin_leaderboard(&state, &config).await {
+                        Ok(text) => text,
+                        Err(err) => {
+                            error!("Failed to pin the leaderboard: {err:?}");
+                            "Failed to pin the leaderboard.".to_string()
+                        }
+                    };
+                    let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+                    if let Err(err) = post_notice(&state, channel, event.user_id, &text).await {
+                        error!("Failed to report the pinned leaderboard: {err:?}");
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Forget { user } => {
+            if !config.is_admin(&event.user_id) {
+                let text = "Only admins can remove the data of users.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+            let Some(user_id) = user else {
+                let text = "Please mention the user whose data should be removed, e.g. \
+                            `/woss forget @user`.";
+                return Ok(Json(ephemeral_response(text.into())));
+            };
+
+            tokio::spawn(
+                async move {
+                    let text = match slack::forget_user(&state, &config, &user_id).await {
+                        Ok(text) => text,
+                        Err(err) => {
+                            error!("Failed to remove the data of {}: {err:?}", user_id.0);
+                            "Failed to remove the data of the user.".to_string()
+                        }
+                    };
+                    let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+                    if let Err(err) = post_notice(&state, channel, event.user_id, &text).await {
+                        error!("Failed to report the removed user data: {err:?}");
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::Backfill => {
+            if !config.is_admin(&event.user_id) {
+                let text = "Only admins can copy the entries from the channel history.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+
+            tokio::spawn(
+                async move {
+                    let text = match slack::backfill_entries(&state, &config).await {
+                        Ok(result) if result.truncated => format!(
+                            "Copied {} entries from the channel history, skipped {} that \
+                             were already stored. Results truncated: Only the latest {} \
+                             pages of the history were read, raise HISTORY_MAX_PAGES to \
+                             copy older entries.",
+                            result.imported, result.skipped, config.history_max_pages
+                        ),
+                        Ok(result) => format!(
+                            "Copied {} entries from the channel history, skipped {} that \
+                             were already stored.",
+                            result.imported, result.skipped
+                        ),
+                        Err(err) => {
+                            error!("Failed to backfill entries: {err:?}");
+                            "Failed to copy the entries from the channel history.".to_string()
+                        }
+                    };
+                    let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+                    if let Err(err) = post_notice(&state, channel, event.user_id, &text).await {
+                        error!("Failed to report the backfill result: {err:?}");
+                    }
+                }
+                .in_current_span(),
+            );
+
+            Ok(Json(loading_response))
+        }
+
+        Subcommand::RecordHours => {
+            if !may_open_modal(&state, &config, &event.user_id).await {
+                let text = "Slow down! Please wait a minute before trying again.";
+                return Ok(Json(ephemeral_response(text.into())));
+            }
+
             // TODO: Pre-fill form with parameters, and if all parameters are available,
             //       don't show form at all.
-            tokio::spawn(async move {
-                let default_country = state.persistence.get_default_country(event.user_id).await;
-                slack::open_oss_modal(&state, event.trigger_id, default_country)
-                    .await
-                    .unwrap();
-            });
+            tokio::spawn(
+                async move {
+                    let language = slack::user_language(&state, &event.user_id).await;
+                    let default_country =
+                        state.persistence.get_default_country(event.user_id).await;
+                    let res = slack::open_oss_modal(
+                        &state,
+                        &config,
+                        event.trigger_id,
+                        language.as_deref(),
+                        default_country,
+                        None,
+                    )
+                    .await;
+                    if let Err(err) = res {
+                        error!("Failed to open the modal: {err:?}");
+                    }
+                }
+                .in_current_span(),
+            );
 
-            Ok(Json(loading_message()))
+            Ok(Json(loading_response))
         }
 
-        None => Ok(Json(SlackCommandEventResponse::new(
-            SlackMessageContent::new().with_text("TODO: Usage information".into()),
+        Subcommand::Unknown { word, suggestion } => {
+            let text = match suggestion {
+                Some(suggestion) => {
+                    format!("Unknown command `{word}`, did you mean `{suggestion}`?")
+                }
+                None => format!("Unknown command `{word}`."),
+            };
+            Ok(Json(ephemeral_response(text)))
+        }
+
+        Subcommand::Usage => Ok(Json(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(usage_text(&config.command_aliases)),
         ))),
     }
 }
 
-fn loading_message() -> SlackCommandEventResponse {
-    let message = LOADING_MESSAGES
-        .choose(&mut rand::thread_rng())
-        .unwrap_or(&"");
+/// The subcommands of `/woss`, parsed from the text following the command
+#[derive(Debug, PartialEq)]
+enum Subcommand {
+    /// The totals of all or a single user's entries, optionally only of entries with the
+    /// given tag, or submitted between the given days (`since=2023-01-01`,
+    /// `until=2023-03-31`). If verbose, each entry is listed with its description and URL.
+    Stats {
+        tag: Option<String>,
+        user: Option<SlackUserId>,
+        verbose: bool,
+        since: Option<String>,
+        until: Option<String>,
+    },
+    /// The hours per team, see `USER_TEAMS`, optionally only of entries with the given tag
+    TeamStats {
+        tag: Option<String>,
+    },
+    /// The hours per week, optionally only of entries with the given tag
+    Trend {
+        tag: Option<String>,
+    },
+    Export,
+    /// The entries of a single user as JSON file, by default of the user that invoked
+    /// the command. Only admins may export the entries of others.
+    ExportJson {
+        user: Option<SlackUserId>,
+    },
+    /// A summary of the given month (`YYYY-MM`) as a file
+    Report {
+        month: Option<String>,
+    },
+    /// Sets the country that is preselected in the modal
+    Office {
+        country: String,
+    },
+    /// The latest entries of all users, optionally how many
+    Recent {
+        count: Option<usize>,
+    },
+    /// The stats of the user that invoked the command
+    Me {
+        verbose: bool,
+    },
+    /// Shows, sets or removes (`off`) the user's goal of hours per quarter
+    Goal {
+        hours: Option<String>,
+    },
+    /// The leaderboard as of the last time it was recomputed, optionally counting each
+    /// URL only once, or the current one of a single office (`office=Germany`)
+    Leaderboard {
+        unique: bool,
+        office: Option<String>,
+    },
+    /// Changes the hours of the user's latest entry with the given URL
+    Fix {
+        url: Option<String>,
+        hours: Option<String>,
+    },
+    /// Recomputes the leaderboard, admins only
+    Recompute,
+    /// Posts and pins the leaderboard in the OSS channel, admins only
+    Pin,
+    /// Deletes the data stored about a user, admins only
+    Forget {
+        user: Option<SlackUserId>,
+    },
+    /// Shows the effective configuration to admins
+    Status,
+    /// Shows which build is running, and for how long
+    Version,
+    /// Copies the entries in the channel history into redis, admins only
+    Backfill,
+    RecordHours,
+    /// Neither a subcommand nor an alias, with the closest match if there is one
+    Unknown {
+        word: String,
+        suggestion: Option<String>,
+    },
+    Usage,
+}
 
-    let mut response = SlackCommandEventResponse::new(
-        SlackMessageContent::new().with_text(format!("Please wait... {message}...")),
-    );
+/// A subcommand of `/woss`, as listed by `/woss help`
+pub struct SubcommandHelp {
+    /// The word the subcommand is invoked with
+    pub word: &'static str,
+    /// E.g. `<url> <hours>`, optional ones in square brackets
+    pub args: &'static str,
+    pub description: &'static str,
+}
+
+/// The subcommands of `/woss`. Aliases map to their words, see
+/// `AppConfig::command_aliases`.
+pub const SUBCOMMANDS: [SubcommandHelp; 17] = [
+    SubcommandHelp {
+        word: "record",
+        args: "",
+        description: "Opens the form to record your hours",
+    },
+    SubcommandHelp {
+        word: "fix",
+        args: "<url> <hours>",
+        description: "Changes the hours of your latest entry with the URL",
+    },
+    SubcommandHelp {
+        word: "stats",
+        args: "[teams|trend] [@user] [tag] [since=YYYY-MM-DD] [until=YYYY-MM-DD] [verbose]",
+        description: "Shows the hours of everyone or of a single user, per team or per week",
+    },
+    SubcommandHelp {
+        word: "recent",
+        args: "[count]",
+        description: "Lists the latest entries",
+    },
+    SubcommandHelp {
+        word: "me",
+        args: "[verbose]",
+        description: "Shows your own stats",
+    },
+    SubcommandHelp {
+        word: "goal",
+        args: "[hours|off]",
+        description: "Shows or sets your goal of hours per quarter",
+    },
+    SubcommandHelp {
+        word: "leaderboard",
+        args: "[unique] [office=<office>]",
+        description: "Shows the leaderboard",
+    },
+    SubcommandHelp {
+        word: "export",
+        args: "[json [@user]]",
+        description: "Sends all entries as CSV (admins only), or your entries as JSON",
+    },
+    SubcommandHelp {
+        word: "report",
+        args: "<YYYY-MM>",
+        description: "Sends a summary of the month",
+    },
+    SubcommandHelp {
+        word: "office",
+        args: "<office>",
+        description: "Sets the office that is preselected in the form",
+    },
+    SubcommandHelp {
+        word: "status",
+        args: "",
+        description: "Shows the configuration (admins only)",
+    },
+    SubcommandHelp {
+        word: "version",
+        args: "",
+        description: "Shows which version is running",
+    },
+    SubcommandHelp {
+        word: "recompute",
+        args: "",
+        description: "Recomputes the leaderboard (admins only)",
+    },
+    SubcommandHelp {
+        word: "pin",
+        args: "",
+        description: "Posts and pins the leaderboard (admins only)",
+    },
+    SubcommandHelp {
+        word: "forget",
+        args: "@user",
+        description: "Removes the data stored about a user (admins only)",
+    },
+    SubcommandHelp {
+        word: "backfill",
+        args: "",
+        description: "Stores the entries of the channel history (admins only)",
+    },
+    SubcommandHelp {
+        word: "help",
+        args: "",
+        description: "Shows this list",
+    },
+];
+
+/// Whether the word is a subcommand, not taking aliases into account
+pub fn is_subcommand(word: &str) -> bool {
+    SUBCOMMANDS.iter().any(|subcommand| subcommand.word == word)
+}
+
+/// Lists the subcommands and the configured aliases, see `/woss help`
+fn usage_text(aliases: &HashMap<String, String>) -> String {
+    let mut lines = vec!["*Usage*".to_string()];
+    for subcommand in &SUBCOMMANDS {
+        let invocation = match subcommand.args {
+            "" => format!("/woss {}", subcommand.word),
+            args => format!("/woss {} {args}", subcommand.word),
+        };
+        lines.push(format!("• `{invocation}` – {}", subcommand.description));
+    }
+
+    if !aliases.is_empty() {
+        let mut aliases: Vec<String> = aliases
+            .iter()
+            .map(|(alias, word)| format!("`{alias}` for `{word}`"))
+            .collect();
+        aliases.sort();
+        lines.push(format!("Aliases: {}", aliases.join(", ")));
+    }
+
+    lines.join("\n")
+}
+
+/// Arguments that make `/woss stats` and `/woss me` list each entry
+const VERBOSE_FLAGS: [&str; 2] = ["-v", "verbose"];
+
+/// Unknown words are only corrected if they are at most this many edits away
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+impl Subcommand {
+    fn parse(text: &str, aliases: &HashMap<String, String>) -> Self {
+        let mut args = text.split_whitespace().peekable();
+        let word = args.next().map(|word| {
+            let word = word.to_lowercase();
+            aliases.get(&word).cloned().unwrap_or(word)
+        });
+
+        match word.as_deref() {
+            Some("stats") if args.peek() == Some(&"teams") => {
+                args.next();
+                Subcommand::TeamStats {
+                    tag: args.next().map(String::from),
+                }
+            }
+            Some("stats") if args.peek() == Some(&"trend") => {
+                args.next();
+                Subcommand::Trend {
+                    tag: args.next().map(String::from),
+                }
+            }
+            Some("stats") => {
+                let mut tag = None;
+                let mut user = None;
+                let mut verbose = false;
+                let mut since = None;
+                let mut until = None;
+                for arg in args {
+                    if VERBOSE_FLAGS.contains(&arg) {
+                        verbose = true;
+                    } else if let Some(date) = arg.strip_prefix("since=") {
+                        since = Some(date.to_string());
+                    } else if let Some(date) = arg.strip_prefix("until=") {
+                        until = Some(date.to_string());
+                    } else if let Some(user_id) = slack::parse_user_mention(arg) {
+                        user = Some(user_id);
+                    } else {
+                        tag = Some(arg.to_string());
+                    }
+                }
+                Subcommand::Stats {
+                    tag,
+                    user,
+                    verbose,
+                    since,
+                    until,
+                }
+            }
+            Some("export") if args.peek() == Some(&"json") => {
+                args.next();
+                Subcommand::ExportJson {
+                    user: args.next().and_then(slack::parse_user_mention),
+                }
+            }
+            Some("export") => Subcommand::Export,
+            Some("report") => Subcommand::Report {
+                month: args.next().map(String::from),
+            },
+            Some("office") => {
+                let country: Vec<&str> = args.collect();
+                Subcommand::Office {
+                    country: country.join(" "),
+                }
+            }
+            Some("recent") => Subcommand::Recent {
+                count: args.next().and_then(|count| count.parse().ok()),
+            },
+            Some("me") => Subcommand::Me {
+                verbose: args.any(|arg| VERBOSE_FLAGS.contains(&arg)),
+            },
+            Some("goal") => Subcommand::Goal {
+                hours: args.next().map(|hours| hours.to_lowercase()),
+            },
+            Some("leaderboard") => {
+                let mut unique = false;
+                let mut office: Option<String> = None;
+                for arg in args {
+                    if arg == "unique" {
+                        unique = true;
+                    } else if let Some(name) = arg.strip_prefix("office=") {
+                        office = Some(name.to_string());
+                    } else if let Some(office) = &mut office {
+                        // Office names may consist of several words
+                        office.push(' ');
+                        office.push_str(arg);
+                    }
+                }
+                Subcommand::Leaderboard { unique, office }
+            }
+            Some("fix") => Subcommand::Fix {
+                url: args.next().map(String::from),
+                hours: args.next().map(String::from),
+            },
+            Some("recompute") => Subcommand::Recompute,
+            Some("pin") => Subcommand::Pin,
+            Some("forget") => Subcommand::Forget {
+                user: args.next().and_then(slack::parse_user_mention),
+            },
+            Some("status") => Subcommand::Status,
+            Some("version") => Subcommand::Version,
+            Some("backfill") => Subcommand::Backfill,
+            Some("record") => Subcommand::RecordHours,
+            Some("help") | None => Subcommand::Usage,
+            Some(word) => Subcommand::Unknown {
+                word: word.to_string(),
+                suggestion: Self::suggest(word, aliases),
+            },
+        }
+    }
+
+    /// Finds the subcommand or alias that is closest to the given unknown word
+    fn suggest(word: &str, aliases: &HashMap<String, String>) -> Option<String> {
+        SUBCOMMANDS
+            .iter()
+            .map(|subcommand| subcommand.word)
+            .chain(aliases.keys().map(String::as_str))
+            .map(|candidate| (edit_distance(word, candidate), candidate))
+            .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+            .min()
+            .map(|(_, candidate)| candidate.to_string())
+    }
+}
+
+/// The Levenshtein distance, i.e. the number of single character insertions, deletions
+/// or substitutions needed to turn one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Opening modals is rate limited per user, see `AppConfig::record_rate_limit`. Reading
+/// stats isn't, as that doesn't create as much load.
+async fn may_open_modal(state: &AppState, config: &AppConfig, user_id: &SlackUserId) -> bool {
+    if config.record_rate_limit == 0 {
+        return true;
+    }
+
+    let res = state
+        .persistence
+        .take_rate_limit_token(
+            "record_hours",
+            user_id,
+            config.record_rate_limit,
+            Duration::from_secs(60),
+        )
+        .await;
+
+    match res {
+        Ok(allowed) => allowed,
+        // Users shouldn't be locked out just because redis is unavailable
+        Err(err) => {
+            warn!("Failed to check the rate limit of {}: {err:?}", user_id.0);
+            true
+        }
+    }
+}
+
+/// Summarizes the effective configuration for admins. Secrets such as tokens, the
+/// signing secret and the redis URL (which may contain a password) are left out.
+async fn status_report(state: &AppState, config: &AppConfig, command: &SlackCommandId) -> String {
+    let storage = match state.persistence.ping().await {
+        _ if state.persistence.is_degraded(config) => {
+            "*degraded*, redis wasn't reachable on startup, data is only kept in memory"
+        }
+        Ok(()) => "reachable",
+        Err(_) => "*not reachable*",
+    };
+    let post_identity = match &config.post_identity {
+        PostIdentity::Contributor { .. } => "contributor",
+        PostIdentity::Single { .. } => "single",
+    };
+    let digest = match &config.digest_schedule {
+        Some(schedule) => format!(
+            "{:?} at {} UTC in <#{}> ({:?})",
+            schedule.weekday, schedule.time, schedule.channel_id, schedule.mode
+        ),
+        None => "disabled".to_string(),
+    };
+    let enabled = |flag: bool| if flag { "enabled" } else { "disabled" };
+
+    let lines = [
+        format!("*Command:* {}", command.0),
+        format!("*OSS channel:* <#{}>", config.slack_oss_channel_id),
+        format!(
+            "*Storage:* {:?}, {storage} (namespace `{}`)",
+            config.storage_backend, config.redis_namespace
+        ),
+        format!("*Post identity:* {post_identity}"),
+        format!(
+            "*Effort colors:* {} / {} hours",
+            config.medium_effort_hours, config.large_effort_hours
+        ),
+        format!("*Max hours per entry:* {}", config.max_hours_per_entry),
+        format!(
+            "*Description:* {}, {} to {} characters",
+            if config.require_description {
+                "required"
+            } else {
+                "optional"
+            },
+            config.min_description_length,
+            config.max_description_length
+        ),
+        format!(
+            "*Rate limit:* {} modals per minute",
+            config.record_rate_limit
+        ),
+        match config.history_max_pages {
+            0 => "*History limit:* none".to_string(),
+            pages => format!("*History limit:* {pages} pages"),
+        },
+        format!("*Webhook:* {}", enabled(config.webhook_url.is_some())),
+        format!("*Weekly digest:* {digest}"),
+        format!("*Dry run:* {}", enabled(config.dry_run)),
+        format!("*Admins:* {}", config.admin_user_ids.len()),
+        format!("*Users with a team:* {}", config.user_teams.len()),
+    ];
+
+    lines.join("\n")
+}
+
+/// Shown while a command is processed if there is no loading message to show, e.g.
+/// because random loading messages are disabled or the messages file is empty
+const FALLBACK_LOADING_MESSAGE: &str = "Working on it…";
+
+/// Shown while a command is processed in the background, e.g. "Please wait...
+/// Reticulating splines...", see `loading_messages`
+fn loading_message(config: &AppConfig) -> SlackCommandEventResponse {
+    let message = if config.random_loading_messages {
+        loading_messages::random()
+    } else {
+        String::new()
+    };
+
+    let text = if message.is_empty() {
+        FALLBACK_LOADING_MESSAGE.to_string()
+    } else {
+        format!("{} {message}...", config.loading_message_prefix)
+    };
+
+    match &config.loading_message_emoji {
+        Some(emoji) => ephemeral_response(format!(":{emoji}: {text}")),
+        None => ephemeral_response(text),
+    }
+}
+
+/// Parses the `since=` and `until=` days of `/woss stats`. Both days are included.
+/// Returns the message to show the user if a day is invalid.
+fn parse_entry_range(
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<slack::EntryRange, String> {
+    let parse_day = |day: &str| {
+        NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| {
+            format!(
+                "Invalid day '{day}', please enter days as YYYY-MM-DD, e.g. `since=2023-01-01`."
+            )
+        })
+    };
+
+    let range = slack::EntryRange {
+        since: since
+            .map(parse_day)
+            .transpose()?
+            .and_then(|day| day.and_hms_opt(0, 0, 0))
+            .map(|date| Utc.from_utc_datetime(&date)),
+        until: until
+            .map(parse_day)
+            .transpose()?
+            .and_then(|day| day.and_hms_nano_opt(23, 59, 59, 999_999_999))
+            .map(|date| Utc.from_utc_datetime(&date)),
+    };
+
+    if let (Some(since), Some(until)) = (range.since, range.until) {
+        if since > until {
+            return Err("The `since` day must not be after the `until` day.".to_string());
+        }
+    }
+
+    Ok(range)
+}
+
+/// Lists the known offices, for commands that take an office as argument
+fn unknown_office_message(office: &str) -> String {
+    let labels: Vec<&str> = countries::all()
+        .iter()
+        .map(|country| country.label.as_str())
+        .collect();
+    format!(
+        "Unknown office '{office}', please use one of: {}",
+        labels.join(", ")
+    )
+}
+
+/// Formats a duration in days, hours and minutes, e.g. "2d 3h 15m"
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// A response to a command that only the user who invoked it can see
+fn ephemeral_response(text: String) -> SlackCommandEventResponse {
+    let mut response = SlackCommandEventResponse::new(SlackMessageContent::new().with_text(text));
     response.response_type = Some(SlackMessageResponseType::Ephemeral);
     response
 }
@@ -121,122 +1365,1010 @@ pub async fn interaction_event_handler(
     Extension(event): Extension<SlackInteractionEvent>,
     Extension(state): Extension<AppState>,
     Extension(config): Extension<AppConfig>,
-) -> Result<String, AppError> {
+) -> Result<axum::response::Response, AppError> {
     trace!("Received interaction event: {:?}", event);
 
     match event {
-        SlackInteractionEvent::Shortcut(s) => match s.callback_id.as_ref() {
-            "record_oss_hours" => {
-                let default_country = state.persistence.get_default_country(s.user.id).await;
-                slack::open_oss_modal(&state, s.trigger_id, default_country)
-                    .await
-                    .unwrap();
-                Ok("".to_string())
+        SlackInteractionEvent::Shortcut(event) => {
+            record_user(&event.user.id);
+            let state = state.for_team(&event.team.id).await;
+            handle_shortcut(event, &state, &config).await?
+        }
+        SlackInteractionEvent::MessageAction(event) => {
+            record_user(&event.user.id);
+            let state = state.for_team(&event.team.id).await;
+            handle_message_action(event, &state, &config).await?
+        }
+        SlackInteractionEvent::BlockActions(event) => {
+            if let Some(user) = &event.user {
+                record_user(&user.id);
+            }
+            let state = state.for_team(&event.team.id).await;
+            handle_block_actions(event, &state, &config).await?
+        }
+        SlackInteractionEvent::ViewSubmission(event) => {
+            record_user(&event.user.id);
+            let state = state.for_team(&event.team.id).await;
+            match handle_view_submission(event, &state, &config).await {
+                Ok(Some(response)) => return Ok(Json(response).into_response()),
+                Ok(None) => {}
+                Err(
+                    err @ (AppError::InternalServerError(_)
+                    | AppError::SlackApi { .. }
+                    | AppError::SlackTimeout { .. }),
+                ) => {
+                    error!("Failed to handle the view submission: {err:?}");
+                    let response = json!({
+                        "response_action": "update",
+                        "view": errors::error_view(errors::INTERNAL_ERROR_MESSAGE),
+                    });
+                    return Ok(Json(response).into_response());
+                }
+                Err(err) => return Err(err),
             }
+        }
+        // Sent when the user cancels the modal, there's nothing to do
+        SlackInteractionEvent::ViewClosed(_) => {}
 
-            callback_id => Err(anyhow!("Unknown short callback ID {callback_id}").into()),
-        },
+        _ => {
+            error!("Received unknown interaction event: {:?}", event);
+            return Err(anyhow!("Received unknown interaction event").into());
+        }
+    }
 
-        SlackInteractionEvent::ViewSubmission(event) => {
-            let Some(view_state) = event.view.state_params.state else {
-                return Err(anyhow!("View submission did not contain state").into());
+    Ok("".into_response())
+}
+
+async fn handle_shortcut(
+    event: SlackInteractionShortcutEvent,
+    state: &AppState,
+    config: &AppConfig,
+) -> Result<(), AppError> {
+    match event.callback_id.as_ref() {
+        "record_oss_hours" => {
+            let language = slack::user_language(state, &event.user.id).await;
+            let default_country = state.persistence.get_default_country(event.user.id).await;
+            slack::open_oss_modal(
+                state,
+                config,
+                event.trigger_id,
+                language.as_deref(),
+                default_country,
+                None,
+            )
+            .await?;
+            Ok(())
+        }
+
+        callback_id => Err(anyhow!("Unknown short callback ID {callback_id}").into()),
+    }
+}
+
+async fn handle_message_action(
+    event: SlackInteractionMessageActionEvent,
+    state: &AppState,
+    config: &AppConfig,
+) -> Result<(), AppError> {
+    match event.callback_id.as_ref() {
+        "edit_oss_hours" => {
+            let (Some(channel), Some(message)) = (event.channel, event.message) else {
+                return Err(anyhow!("Message action did not contain the message").into());
+            };
+
+            let user_req = SlackApiUsersInfoRequest {
+                user: event.user.id.clone(),
+                // The locale is used to show the modal in the user's language
+                include_locale: Some(true),
+            };
+            let res = state
+                .slack
+                .users_info(&user_req)
+                .await
+                .map_err(AppError::slack_api("users.info"))?;
+
+            let entry = match slack::entries_from_message(&message).into_iter().next() {
+                Some(entry) if Some(&entry.username) == res.user.name.as_ref() => entry,
+                Some(_) => {
+                    let text = "You can only edit contributions that you recorded yourself.";
+                    return post_notice(state, channel.id, event.user.id, text).await;
+                }
+                None => {
+                    let text = "This message is not an open source contribution.";
+                    return post_notice(state, channel.id, event.user.id, text).await;
+                }
+            };
+
+            let edited_message = slack::EditedMessage {
+                channel: channel.id,
+                ts: message.origin.ts,
+                co_authors: entry.co_authors.clone(),
+            };
+            let language = res
+                .user
+                .locale
+                .as_ref()
+                .map(|locale| slack::locale_language(&locale.0));
+            slack::open_edit_modal(
+                state,
+                config,
+                event.trigger_id,
+                language.as_deref(),
+                &edited_message,
+                &entry,
+            )
+            .await?;
+            Ok(())
+        }
+
+        callback_id => Err(anyhow!("Unknown message action callback ID {callback_id}").into()),
+    }
+}
+
+async fn handle_block_actions(
+    event: SlackInteractionBlockActionsEvent,
+    state: &AppState,
+    config: &AppConfig,
+) -> Result<(), AppError> {
+    let actions = event.actions.unwrap_or_default();
+    let Some(action) = actions.first() else {
+        return Err(anyhow!("Block actions event did not contain an action").into());
+    };
+
+    match action.action_id.as_ref() {
+        // The "Record hours" button on the App Home tab
+        "record_oss_hours" => {
+            let (language, default_country) = match event.user {
+                Some(user) => (
+                    slack::user_language(state, &user.id).await,
+                    state.persistence.get_default_country(user.id).await,
+                ),
+                None => (None, None),
+            };
+            slack::open_oss_modal(
+                state,
+                config,
+                event.trigger_id,
+                language.as_deref(),
+                default_country,
+                None,
+            )
+            .await?;
+            Ok(())
+        }
+
+        // The button offered after reacting to a message, see `handle_quick_log_reaction`
+        "quick_log" => {
+            let (language, default_country) = match event.user {
+                Some(user) => (
+                    slack::user_language(state, &user.id).await,
+                    state.persistence.get_default_country(user.id).await,
+                ),
+                None => (None, None),
+            };
+            slack::open_oss_modal(
+                state,
+                config,
+                event.trigger_id,
+                language.as_deref(),
+                default_country,
+                action.value.clone(),
+            )
+            .await?;
+            Ok(())
+        }
+
+        "clear_form" => {
+            let (SlackInteractionActionContainer::View(container), Some(view)) =
+                (&event.container, &event.view)
+            else {
+                return Err(anyhow!("The clear form button was not part of a view").into());
+            };
+
+            let language = match &event.user {
+                Some(user) => slack::user_language(state, &user.id).await,
+                None => None,
             };
+            let metadata = slack::ModalMetadata::from_view(view);
+            slack::clear_modal(
+                state,
+                config,
+                container.view_id.clone(),
+                language.as_deref(),
+                &metadata,
+            )
+            .await?;
+            Ok(())
+        }
 
-            let number_of_hours = view_state.input_value("number_of_hours")?;
-            let url = view_state.input_value("url")?;
-            let description = view_state.input_value("description")?;
-            let country = view_state.select_value("country")?;
+        action_id => Err(anyhow!("Unknown action ID {action_id}").into()),
+    }
+}
 
-            info!("Received a new submission: {number_of_hours} {url} '{description}' {country}");
+/// Handles submissions of the modal. The first submission is answered with a preview
+/// of the entry, which replaces the modal. The entry is only posted once the user
+/// confirms the preview.
+async fn handle_view_submission(
+    event: SlackInteractionViewSubmissionEvent,
+    state: &AppState,
+    config: &AppConfig,
+) -> Result<Option<serde_json::Value>, AppError> {
+    let metadata = slack::ModalMetadata::from_view(&event.view.view);
 
-            let parsed_hours = number_of_hours
-                .parse::<i16>()
-                .context("number_of_hours is not an i16")?;
+    let Some(input) = metadata.confirmed_input.clone() else {
+        let Some(view_state) = event.view.state_params.state else {
+            return Err(anyhow!("View submission did not contain state").into());
+        };
 
-            if parsed_hours <= 0 {
-                return Err(AppError::InputValidationError {
-                    field_name: "number_of_hours".to_string(),
-                    message: "Number of hours must be greater than 0".to_string(),
-                });
+        let input = slack::ModalInput::from_view_state(&view_state)?;
+        let validation = match validate_input(&input, config) {
+            Ok((hours, url)) if config.verify_url_reachable => {
+                check_url_reachable(&url).await.map(|()| (hours, url))
             }
+            result => result,
+        };
+        // The block IDs change when the form is cleared, see `slack::clear_modal`
+        let block_id = |field_name: String| view_state.block_id(&field_name).unwrap_or(field_name);
+        let (parsed_hours, _) = validation.map_err(|err| match err {
+            AppError::InputValidationError {
+                field_name,
+                message,
+            } => AppError::InputValidationError {
+                field_name: block_id(field_name),
+                message,
+            },
+            AppError::InputValidationErrors(errors) => AppError::InputValidationErrors(
+                errors
+                    .into_iter()
+                    .map(|(field_name, message)| (block_id(field_name), message))
+                    .collect(),
+            ),
+            err => err,
+        })?;
+
+        let metadata = slack::ModalMetadata {
+            confirmed_input: Some(input.clone()),
+            ..metadata
+        };
+        let view = slack::entry_preview_view(&input, parsed_hours, &metadata)?;
 
-            let parsed_url = Url::parse(&url).map_err(|_err| AppError::InputValidationError {
-                field_name: "url".to_string(),
-                message: "Not a valid URL".to_string(),
-            })?;
+        return Ok(Some(json!({
+            "response_action": "update",
+            "view": view,
+        })));
+    };
+
+    let slack::ModalInput {
+        number_of_hours,
+        url,
+        description,
+        country,
+        project,
+        tags,
+        co_authors,
+    } = input.clone();
+    let edited_message = metadata.edited_message;
+
+    debug!("Received a new submission: {number_of_hours} {url} '{description}' {country}");
+
+    let (parsed_hours, parsed_url) = validate_input(&input, config)?;
+
+    let user_id = event.user.id;
+    let user_req = SlackApiUsersInfoRequest {
+        user: user_id,
+        include_locale: None,
+    };
+    let res = state
+        .slack
+        .users_info(&user_req)
+        .await
+        .map_err(AppError::slack_api("users.info"))?;
+
+    let profile_image = slack::profile_image_url(&res.user, config.profile_image_size);
+
+    let Some(username) = res.user.name else {
+        return Err(anyhow!("The user information did not contain a username").into());
+    };
+
+    // Edited entries keep the date they were originally submitted on
+    let submitted_at = match &edited_message {
+        Some(edited_message) => slack::ts_to_datetime(&edited_message.ts),
+        None => Some(chrono::Utc::now()),
+    };
+
+    // Co-authors can't be preselected when editing, as only their usernames are stored,
+    // so the previous ones are kept unless others were selected
+    let co_authors = match &edited_message {
+        Some(edited_message) if co_authors.is_empty() => edited_message.co_authors.clone(),
+        _ => co_author_usernames(state, &co_authors, &username).await?,
+    };
+
+    let attachment = OpenSourceAttachment {
+        username: username.clone(),
+        number_of_hours: parsed_hours,
+        country: country.clone(),
+        url: parsed_url,
+        project,
+        description: description.clone(),
+        tags,
+        co_authors,
+        submitted_at,
+    };
+
+    let content = entry_message_content(&attachment, config)?;
+
+    if let Some(edited_message) = edited_message {
+        info!(
+            "Updating the entry of {username} posted at {}",
+            edited_message.ts.0
+        );
+        let req = SlackApiChatUpdateRequest {
+            channel: edited_message.channel.clone(),
+            content,
+            ts: edited_message.ts.clone(),
+            as_user: None,
+            link_names: None,
+            parse: None,
+            reply_broadcast: None,
+        };
+        state
+            .slack
+            .chat_update(&req)
+            .await
+            .map_err(AppError::slack_api("chat.update"))?;
+        state
+            .persistence
+            .store_entry(&edited_message.channel, &edited_message.ts, &attachment)
+            .await?;
+        state.entries_cache.invalidate();
+        log_submission(&attachment, true);
+        return Ok(None);
+    }
+
+    // Slack sometimes re-delivers interaction payloads, and users may submit twice
+    let fingerprint = submission_fingerprint(&res.user.id, &attachment);
+    let is_new_submission = state
+        .persistence
+        .record_submission(&fingerprint, DUPLICATE_SUBMISSION_WINDOW)
+        .await?;
+
+    if !is_new_submission {
+        info!("Ignoring duplicate submission from {username}");
+        let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+        let text = "This contribution was already recorded.";
+        post_notice(state, channel, res.user.id, text).await?;
+        return Ok(None);
+    }
 
-            if !parsed_url.scheme().starts_with("http") {
-                return Err(AppError::InputValidationError {
-                    field_name: "url".to_string(),
-                    message: "URL should point to an HTTP or HTTPS resource".to_string(),
-                });
+    let (post_username, post_icon_url) = match &config.post_identity {
+        PostIdentity::Contributor { suffix } => (
+            Some(contributor_display_name(&username, suffix)),
+            profile_image,
+        ),
+        PostIdentity::Single { username, icon_url } => (username.clone(), icon_url.clone()),
+    };
+
+    // Until the entry is stored, a failure must not make retries look like duplicates
+    let posted: Result<_, AppError> = async {
+        let thread_ts = if config.features.project_threads {
+            Some(project_thread(state, config, &attachment).await?)
+        } else {
+            None
+        };
+
+        let req = SlackApiChatPostMessageRequest {
+            channel: SlackChannelId(config.slack_oss_channel_id.clone()),
+            content,
+            as_user: None,
+            icon_emoji: None,
+            icon_url: post_icon_url,
+            link_names: None,
+            parse: None,
+            thread_ts,
+            username: post_username,
+            reply_broadcast: None,
+            unfurl_links: None,
+            unfurl_media: None,
+        };
+
+        let posted = state
+            .slack
+            .chat_post_message(&req)
+            .await
+            .map_err(AppError::slack_api("chat.postMessage"))?;
+        state
+            .persistence
+            .store_entry(&posted.channel, &posted.ts, &attachment)
+            .await?;
+
+        Ok(posted)
+    }
+    .await;
+    let posted = match posted {
+        Ok(posted) => posted,
+        Err(err) => {
+            if let Err(forget_err) = state.persistence.forget_submission(&fingerprint).await {
+                warn!("Failed to forget the failed submission: {forget_err:?}");
             }
+            return Err(err);
+        }
+    };
+    state.entries_cache.invalidate();
+    log_submission(&attachment, false);
 
-            let user_id = event.user.id;
-            let user_req = SlackApiUsersInfoRequest {
-                user: user_id,
-                include_locale: None,
-            };
-            let res = state.get_session().users_info(&user_req).await.unwrap();
+    // Slack expects a response within a few seconds, so the webhook is called in the background
+    let webhook_config = config.clone();
+    let webhook_entry = attachment.clone();
+    tokio::spawn(
+        async move { webhook::notify(&webhook_config, &webhook_entry).await }.in_current_span(),
+    );
 
-            let profile_image = res
-                .user
-                .profile
-                .and_then(|profile| profile.icon)
-                .and_then(|icon| icon.images)
-                .and_then(|images| images.resolutions.last().cloned())
-                .map(|resolution| resolution.1);
-
-            let Some(username) = res.user.name else {
-                return Err(anyhow!("The user information did not contain a username").into());
-            };
-
-            let attachment = OpenSourceAttachment {
-                username: username.clone(),
-                number_of_hours: parsed_hours,
-                country: country.clone(),
-                url: parsed_url,
-                description: description.clone(),
-            };
-
-            let req = SlackApiChatPostMessageRequest {
-                channel: SlackChannelId(config.slack_oss_channel_id.clone()),
-                content: SlackMessageContent::new().with_attachments(vec![
-                    SlackMessageAttachment {
-                        id: None,
-                        color: Some("good".to_string()),
-                        fallback: None,
-                        title: None,
-                        fields: Some(attachment.clone().into()),
-                        mrkdwn_in: None,
-                    },
-                ]),
-                as_user: None,
-                icon_emoji: None,
-                icon_url: profile_image,
-                link_names: None,
-                parse: None,
-                thread_ts: None,
-                username: Some(format!("{username} via Wizard of OSS")),
-                reply_broadcast: None,
-                unfurl_links: None,
-                unfurl_media: None,
-            };
-
-            state.get_session().chat_post_message(&req).await.unwrap();
-            state
-                .persistence
-                .set_default_country(res.user.id, attachment.country)
-                .await?;
+    // The reaction is just decoration, so failing to add it shouldn't fail the submission
+    if config.features.reactions {
+        let reaction_req = SlackApiReactionsAddRequest {
+            channel: posted.channel,
+            name: SlackReactionName(config.slack_success_reaction.clone()),
+            timestamp: posted.ts,
+        };
+        if let Err(err) = state.slack.reactions_add(&reaction_req).await {
+            debug!("Failed to add reaction to the posted entry: {err}");
+        }
+    }
+    state
+        .persistence
+        .set_default_country(res.user.id, attachment.country)
+        .await?;
+
+    Ok(None)
+}
+
+/// Logs a recorded entry as a single event with stable field names, so that submissions
+/// can be analyzed from the logs, e.g. with `LOG_FORMAT=json`. The description and the
+/// full URL are left out, as they are free text.
+fn log_submission(attachment: &OpenSourceAttachment, edited: bool) {
+    info!(
+        target: "oss_bot::submission",
+        user = %attachment.username,
+        hours = attachment.number_of_hours,
+        country = %attachment.country,
+        url_host = attachment.url.host_str().unwrap_or_default(),
+        edited,
+        "Recorded a submission"
+    );
+}
+
+/// Returns the message that entries of the entry's project are posted as replies to.
+/// The message is posted when the first entry of a project is submitted.
+async fn project_thread(
+    state: &AppState,
+    config: &AppConfig,
+    attachment: &OpenSourceAttachment,
+) -> Result<SlackTs, AppError> {
+    // Project names entered by users may differ in case only
+    let project = attachment.project_name().to_lowercase();
+    if let Some(ts) = state.persistence.get_project_thread(&project).await {
+        return Ok(ts);
+    }
+
+    let req = SlackApiChatPostMessageRequest {
+        channel: SlackChannelId(config.slack_oss_channel_id.clone()),
+        content: SlackMessageContent::new()
+            .with_text(format!("Contributions to *{}*", attachment.project_name())),
+        as_user: None,
+        icon_emoji: None,
+        icon_url: None,
+        link_names: None,
+        parse: None,
+        thread_ts: None,
+        username: None,
+        reply_broadcast: None,
+        unfurl_links: None,
+        unfurl_media: None,
+    };
+    let posted = state
+        .slack
+        .chat_post_message(&req)
+        .await
+        .map_err(AppError::slack_api("chat.postMessage"))?;
+    info!("Started the thread of project {project} at {}", posted.ts.0);
+    state
+        .persistence
+        .set_project_thread(&project, &posted.ts)
+        .await?;
+
+    Ok(posted.ts)
+}
+
+/// Changes the hours of the user's latest entry with the given URL, both in the message
+/// it was posted as and in redis. Returns the reply to the user.
+async fn fix_entry_hours(
+    state: &AppState,
+    config: &AppConfig,
+    user_id: &SlackUserId,
+    url: &Url,
+    hours: f64,
+) -> Result<String, AppError> {
+    let user_req = SlackApiUsersInfoRequest {
+        user: user_id.clone(),
+        include_locale: None,
+    };
+    let res = state
+        .slack
+        .users_info(&user_req)
+        .await
+        .map_err(AppError::slack_api("users.info"))?;
+    let Some(username) = res.user.name else {
+        return Err(anyhow!("The user information did not contain a username").into());
+    };
+
+    let normalized_url = models::normalize_url(url);
+    let entries: Vec<_> = state
+        .persistence
+        .get_entries_by_message()
+        .await?
+        .into_iter()
+        .filter(|(_, _, entry)| entry.normalized_url() == normalized_url)
+        .collect();
+
+    if entries.is_empty() {
+        return Ok(format!("There is no entry for {url}."));
+    }
+
+    // Entries are sorted newest first
+    let Some((channel, ts, mut entry)) = entries
+        .into_iter()
+        .find(|(_, _, entry)| entry.username == username)
+    else {
+        return Ok(format!(
+            "You can only fix your own entries, the entries for {url} were recorded by \
+             someone else."
+        ));
+    };
 
-            Ok("".to_string())
+    let previous_hours = entry.number_of_hours;
+    entry.number_of_hours = hours;
+
+    info!(
+        "Changing the hours of the entry of {username} posted at {}",
+        ts.0
+    );
+    let req = SlackApiChatUpdateRequest {
+        channel: channel.clone(),
+        content: entry_message_content(&entry, config)?,
+        ts: ts.clone(),
+        as_user: None,
+        link_names: None,
+        parse: None,
+        reply_broadcast: None,
+    };
+    state
+        .slack
+        .chat_update(&req)
+        .await
+        .map_err(AppError::slack_api("chat.update"))?;
+    state.persistence.store_entry(&channel, &ts, &entry).await?;
+    state.entries_cache.invalidate();
+
+    Ok(format!(
+        "Changed the hours of your entry for {url} from {} to {}.",
+        models::display_hours(previous_hours),
+        models::display_hours(hours)
+    ))
+}
+
+/// Parses the number of hours of an entry, which must be within the configured limit
+fn validate_hours(number_of_hours: &str, config: &AppConfig) -> Result<f64, AppError> {
+    let Some(parsed_hours) = models::parse_hours(number_of_hours) else {
+        return Err(AppError::InputValidationError {
+            field_name: "number_of_hours".to_string(),
+            message: "Please enter a number or duration, e.g. 4, 4.5, 4:30 or 4h30m".to_string(),
+        });
+    };
+
+    if parsed_hours <= 0.0 {
+        return Err(AppError::InputValidationError {
+            field_name: "number_of_hours".to_string(),
+            message: "Number of hours must be greater than 0".to_string(),
+        });
+    }
+
+    if parsed_hours > config.max_hours_per_entry {
+        return Err(AppError::InputValidationError {
+            field_name: "number_of_hours".to_string(),
+            message: format!(
+                "That's more than {} hours, please split large contributions into \
+                 multiple entries",
+                config.max_hours_per_entry
+            ),
+        });
+    }
+
+    Ok(parsed_hours)
+}
+
+/// Validates all fields of the modal, and returns the parsed number of hours and URL. If
+/// several fields are invalid, all of their errors are returned, so that the user can
+/// fix them at once.
+fn validate_input(input: &slack::ModalInput, config: &AppConfig) -> Result<(f64, Url), AppError> {
+    let hours = validate_hours(&input.number_of_hours, config);
+    let description = validate_description(&input.description, config);
+    let country = validate_country(&input.country);
+    let url = validate_url(&input.url, config);
+
+    match (hours, description, country, url) {
+        (Ok(hours), Ok(()), Ok(()), Ok(url)) => Ok((hours, url)),
+        (hours, description, country, url) => {
+            let errors = [hours.err(), description.err(), country.err(), url.err()]
+                .into_iter()
+                .flatten()
+                .filter_map(|err| match err {
+                    AppError::InputValidationError {
+                        field_name,
+                        message,
+                    } => Some((field_name, message)),
+                    _ => None,
+                })
+                .collect();
+            Err(AppError::InputValidationErrors(errors))
         }
+    }
+}
 
-        _ => {
-            error!("Received unknown interaction event: {:?}", event);
-            return Err(anyhow!("Received unknown interaction event").into());
+fn validate_description(description: &str, config: &AppConfig) -> Result<(), AppError> {
+    let trimmed_length = description.trim().chars().count();
+    if config.require_description && trimmed_length == 0 {
+        return Err(AppError::InputValidationError {
+            field_name: "description".to_string(),
+            message: "Please describe your contribution".to_string(),
+        });
+    }
+
+    if config.require_description && trimmed_length < config.min_description_length {
+        return Err(AppError::InputValidationError {
+            field_name: "description".to_string(),
+            message: format!(
+                "Please describe your contribution in at least {} characters",
+                config.min_description_length
+            ),
+        });
+    }
+
+    let description_length = description.chars().count();
+    if description_length > config.max_description_length {
+        return Err(AppError::InputValidationError {
+            field_name: "description".to_string(),
+            message: format!(
+                "Description is too long ({description_length} characters), please keep it \
+                 under {} characters",
+                config.max_description_length
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+fn validate_country(country: &str) -> Result<(), AppError> {
+    if country.is_empty() {
+        return Err(AppError::InputValidationError {
+            field_name: "country".to_string(),
+            message: "Please select your office".to_string(),
+        });
+    }
+
+    // The modal only offers valid countries, but the values could be outdated, e.g. if
+    // the country list changed while the modal was open
+    if countries::find(country).is_none() {
+        return Err(AppError::InputValidationError {
+            field_name: "country".to_string(),
+            message: "Please select one of the offered countries".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+fn validate_url(url: &str, config: &AppConfig) -> Result<Url, AppError> {
+    let parsed_url = Url::parse(url).map_err(|_err| AppError::InputValidationError {
+        field_name: "url".to_string(),
+        message: "Not a valid URL".to_string(),
+    })?;
+
+    if !parsed_url.scheme().starts_with("http") {
+        return Err(AppError::InputValidationError {
+            field_name: "url".to_string(),
+            message: "URL should point to an HTTP or HTTPS resource".to_string(),
+        });
+    }
+
+    if !config.is_url_host_allowed(parsed_url.host_str().unwrap_or_default()) {
+        return Err(AppError::InputValidationError {
+            field_name: "url".to_string(),
+            message: format!(
+                "Only URLs of these sites are accepted: {}",
+                config.allowed_url_hosts.join(", ")
+            ),
+        });
+    }
+
+    Ok(parsed_url)
+}
+
+/// Slack expects a response to the submission within 3 seconds, which includes this check
+const URL_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Sends a HEAD request to the URL to catch typos and dead links, see
+/// `AppConfig::verify_url_reachable`. Servers that don't support HEAD requests are given
+/// the benefit of the doubt, as are redirects. URLs that don't point to a public address
+/// are rejected without a request, so that users can't make the bot probe internal
+/// services.
+async fn check_url_reachable(url: &Url) -> Result<(), AppError> {
+    let unreachable = |message: &str| AppError::InputValidationError {
+        field_name: "url".to_string(),
+        message: message.to_string(),
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(unreachable("Only http and https URLs are accepted"));
+    }
+
+    let addresses: Vec<IpAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![ip.into()],
+        Some(Host::Ipv6(ip)) => vec![ip.into()],
+        Some(Host::Domain(domain)) => {
+            let port = url.port_or_known_default().unwrap_or(80);
+            let lookup = tokio::net::lookup_host((domain, port));
+            match tokio::time::timeout(URL_CHECK_TIMEOUT, lookup).await {
+                Ok(Ok(addresses)) => addresses.map(|address| address.ip()).collect(),
+                Ok(Err(err)) => {
+                    debug!("Failed to resolve {url}: {err}");
+                    vec![]
+                }
+                Err(_) => vec![],
+            }
+        }
+        None => vec![],
+    };
+    if addresses.is_empty() {
+        return Err(unreachable("The URL couldn't be reached, please check it"));
+    }
+    if !addresses.iter().all(|ip| is_public_ip(*ip)) {
+        debug!("{url} resolves to a non-public address: {addresses:?}");
+        return Err(unreachable(
+            "The URL doesn't point to a public website, please check it",
+        ));
+    }
+
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client: hyper::Client<_, Body> = hyper::Client::builder().build(connector);
+    let req = hyper::Request::head(url.as_str())
+        .header("User-Agent", "Wizard of OSS")
+        .body(Body::empty())
+        .map_err(anyhow::Error::from)?;
+
+    let res = match tokio::time::timeout(URL_CHECK_TIMEOUT, client.request(req)).await {
+        Ok(Ok(res)) => res,
+        Ok(Err(err)) => {
+            debug!("Failed to reach {url}: {err}");
+            return Err(unreachable("The URL couldn't be reached, please check it"));
         }
+        Err(_) => {
+            return Err(unreachable(
+                "The URL didn't respond in time, please check it",
+            ))
+        }
+    };
+
+    let status = res.status();
+    if status == http::StatusCode::METHOD_NOT_ALLOWED
+        || !(status.is_client_error() || status.is_server_error())
+    {
+        return Ok(());
     }
+
+    debug!("{url} responded with {status}");
+    Err(unreachable(&format!(
+        "The URL responded with an error ({status}), please check it"
+    )))
+}
+
+/// Whether the address is reachable from the internet, as opposed to e.g. loopback,
+/// private networks or the link-local range with the cloud metadata service
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            // Shared address space of carrier-grade NATs, 100.64.0.0/10
+            let shared = first == 100 && (second & 0xc0) == 64;
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || shared
+                || first == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(ip.into());
+            }
+            let first = ip.segments()[0];
+            // Unique local addresses, fc00::/7, and link-local ones, fe80::/10
+            let unique_local = (first & 0xfe00) == 0xfc00;
+            let link_local = (first & 0xffc0) == 0xfe80;
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || unique_local
+                || link_local)
+        }
+    }
+}
+
+/// Adds the user to the span of the current request, see `server::request_span`
+fn record_user(user_id: &SlackUserId) {
+    Span::current().record("user_id", user_id.0.as_str());
+}
+
+/// Posts a message in the given channel that only the given user can see
+async fn post_notice(
+    state: &AppState,
+    channel: SlackChannelId,
+    user: SlackUserId,
+    text: &str,
+) -> Result<(), AppError> {
+    let req = SlackApiChatPostEphemeralRequest {
+        channel,
+        user,
+        content: SlackMessageContent::new().with_text(text.into()),
+        as_user: None,
+        icon_emoji: None,
+        icon_url: None,
+        link_names: None,
+        parse: None,
+        thread_ts: None,
+        username: None,
+    };
+    state
+        .slack
+        .chat_post_ephemeral(&req)
+        .await
+        .map_err(AppError::slack_api("chat.postEphemeral"))?;
+    Ok(())
+}
+
+/// Looks up the usernames of the selected co-authors, leaving out the author
+async fn co_author_usernames(
+    state: &AppState,
+    user_ids: &[String],
+    author: &str,
+) -> Result<Vec<String>, AppError> {
+    let mut usernames: Vec<String> = vec![];
+
+    for user_id in user_ids {
+        let req = SlackApiUsersInfoRequest {
+            user: SlackUserId(user_id.clone()),
+            include_locale: None,
+        };
+        let res = state
+            .slack
+            .users_info(&req)
+            .await
+            .map_err(AppError::slack_api("users.info"))?;
+
+        match res.user.name {
+            Some(name) if name != author && !usernames.contains(&name) => usernames.push(name),
+            Some(_) => {}
+            None => warn!("The user information of co-author {user_id} did not contain a username"),
+        }
+    }
+
+    Ok(usernames)
+}
+
+/// The message an entry is posted as. The title and footer are only added if configured.
+fn entry_message_content(
+    attachment: &OpenSourceAttachment,
+    config: &AppConfig,
+) -> Result<SlackMessageContent, AppError> {
+    let mut content = SlackMessageContent::new().with_attachments(vec![SlackMessageAttachment {
+        id: None,
+        color: Some(attachment_color(attachment.number_of_hours, config).to_string()),
+        fallback: None,
+        title: config.attachment_title.clone(),
+        fields: Some(attachment.clone().into()),
+        mrkdwn_in: None,
+    }]);
+
+    if let Some(template) = &config.success_message {
+        content = content.with_text(message_template::render(template, attachment));
+    }
+
+    // Slack-morphism's attachments don't support footers, so a context block is used
+    // instead, which is what Slack recommends in place of attachment footers anyway
+    if let Some(footer) = &config.attachment_footer {
+        let mut elements = vec![];
+        if let Some(icon_url) = &config.attachment_footer_icon_url {
+            elements.push(json!({ "type": "image", "image_url": icon_url, "alt_text": "icon" }));
+        }
+        elements.push(json!({ "type": "mrkdwn", "text": footer }));
+
+        let block: SlackBlock =
+            serde_json::from_value(json!({ "type": "context", "elements": elements }))
+                .map_err(anyhow::Error::from)?;
+        content = content.with_blocks(vec![block]);
+    }
+
+    Ok(content)
+}
+
+/// The attachment color gives a quick visual signal of the size of a contribution
+fn attachment_color(hours: f64, config: &AppConfig) -> &'static str {
+    if hours >= config.large_effort_hours {
+        "#daa520"
+    } else if hours >= config.medium_effort_hours {
+        "#439fe0"
+    } else {
+        "good"
+    }
+}
+
+/// Slack cuts off longer usernames of posted messages
+const MAX_POST_USERNAME_LENGTH: usize = 80;
+
+/// The name an entry is posted under, e.g. "alice via Wizard of OSS". If that's too long,
+/// the contributor's name is shortened rather than the suffix, unless the suffix alone
+/// is too long.
+fn contributor_display_name(username: &str, suffix: &str) -> String {
+    let name = format!("{username}{suffix}");
+    if name.chars().count() <= MAX_POST_USERNAME_LENGTH {
+        return name;
+    }
+
+    // One character is needed for the ellipsis
+    let available = MAX_POST_USERNAME_LENGTH.saturating_sub(suffix.chars().count() + 1);
+    if available == 0 {
+        return name.chars().take(MAX_POST_USERNAME_LENGTH).collect();
+    }
+
+    let shortened: String = username.chars().take(available).collect();
+    format!("{shortened}…{suffix}")
+}
+
+/// Identical submissions within this window are considered duplicates
+const DUPLICATE_SUBMISSION_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Identifies a submission by who submitted what on which day. The fingerprint is
+/// stored, so it has to stay the same across releases, which `DefaultHasher` doesn't
+/// guarantee.
+fn submission_fingerprint(user_id: &SlackUserId, attachment: &OpenSourceAttachment) -> String {
+    let date = attachment
+        .submitted_at
+        .map(|date| date.date_naive().to_string())
+        .unwrap_or_default();
+    let parts = [
+        user_id.0.as_str(),
+        attachment.url.as_str(),
+        &attachment.number_of_hours.to_string(),
+        &attachment.description,
+        &date,
+    ];
+
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        // Separates the parts, so that e.g. "ab" + "c" and "a" + "bc" differ
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
 }
 
 pub fn error_handler(
@@ -244,6 +2376,472 @@ pub fn error_handler(
     _client: Arc<SlackHyperClient>,
     _states: SlackClientEventsUserState,
 ) -> http::StatusCode {
-    error!("{:#?}", err);
+    if let Some(status) = handle_signature_error(err.as_ref()) {
+        return status;
+    }
+
+    match err.downcast_ref::<SlackClientError>() {
+        Some(slack_err) => errors::log_slack_error(slack_err),
+        None => error!("{:#?}", err),
+    }
     http::StatusCode::BAD_REQUEST
 }
+
+/// Logs requests that failed the signature check, which are either not from Slack or
+/// can't be verified because `SLACK_SIGNING_SECRET` or the server clock is wrong. The
+/// clock skew is logged, since Slack's timestamp is part of the signature.
+/// Returns `None` if the error isn't about the signature.
+fn handle_signature_error(err: &(dyn std::error::Error + 'static)) -> Option<http::StatusCode> {
+    if err.is::<SlackEventAbsentSignatureError>() {
+        warn!("Rejecting a request without Slack signature headers");
+        return Some(http::StatusCode::UNAUTHORIZED);
+    }
+
+    match err.downcast_ref::<SlackEventSignatureVerifierError>()? {
+        SlackEventSignatureVerifierError::AbsentSignatureError(_) => {
+            warn!("Rejecting a request without Slack signature headers");
+        }
+        SlackEventSignatureVerifierError::WrongSignatureError(err) => {
+            let skew = err
+                .ts
+                .parse::<i64>()
+                .map(|ts| format!("{}s", chrono::Utc::now().timestamp() - ts))
+                .unwrap_or_else(|_| "unknown".to_string());
+            warn!(
+                "Rejecting a request with an invalid Slack signature (clock skew: {skew}). If \
+                 all requests are rejected, check SLACK_SIGNING_SECRET and the server clock."
+            );
+        }
+        err => {
+            error!("Failed to verify the Slack signature of a request: {err}");
+            return Some(http::StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+
+    Some(http::StatusCode::UNAUTHORIZED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::mock::RecordingGateway;
+
+    const TEAM_ID: &str = "T0000000000";
+    const USER_ID: &str = "U0000000001";
+
+    fn modal_input() -> slack::ModalInput {
+        slack::ModalInput {
+            number_of_hours: "1:30".to_string(),
+            url: "https://github.com/x3ro/wizard-of-oss/pull/1".to_string(),
+            description: "Fixed the build".to_string(),
+            country: "germany".to_string(),
+            project: None,
+            tags: vec![],
+            co_authors: vec![],
+        }
+    }
+
+    /// A submission of the modal, as sent by Slack when the user confirms the preview
+    fn view_submission(metadata: &slack::ModalMetadata) -> SlackInteractionEvent {
+        serde_json::from_value(json!({
+            "type": "view_submission",
+            "team": { "id": TEAM_ID },
+            "user": { "id": USER_ID },
+            "view": {
+                "id": "V0000000000",
+                "team_id": TEAM_ID,
+                "hash": "0",
+                "type": "modal",
+                "title": { "type": "plain_text", "text": "Wizard of OSS" },
+                "blocks": [],
+                "private_metadata": serde_json::to_string(metadata).unwrap(),
+            },
+        }))
+        .unwrap()
+    }
+
+    fn command(text: &str) -> SlackCommandEvent {
+        serde_json::from_value(json!({
+            "team_id": TEAM_ID,
+            "channel_id": "COSS",
+            "user_id": USER_ID,
+            "command": "/woss",
+            "text": text,
+            "response_url": "https://hooks.slack.com/commands/T0000000000/1",
+            "trigger_id": "1.2.3",
+        }))
+        .unwrap()
+    }
+
+    async fn run_command(gateway: &RecordingGateway, text: &str) -> SlackCommandEventResponse {
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        let Json(response) = command_event_handler(
+            Extension(command(text)),
+            Extension(state),
+            Extension(AppConfig::for_tests()),
+        )
+        .await
+        .unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn stats_command_responds_with_loading_message() {
+        let gateway = RecordingGateway::new();
+        let response = run_command(&gateway, "stats").await;
+
+        assert_eq!(
+            response.response_type,
+            Some(SlackMessageResponseType::Ephemeral)
+        );
+        assert_eq!(
+            response.content.text.as_deref(),
+            Some(FALLBACK_LOADING_MESSAGE)
+        );
+        let posted = gateway.wait_for("chat.postEphemeral").await;
+        assert_eq!(posted["user"], USER_ID);
+        assert_eq!(posted["text"], "No contributions have been recorded yet.");
+        assert_eq!(gateway.endpoints(), ["chat.postEphemeral"]);
+    }
+
+    #[tokio::test]
+    async fn leaderboard_command_responds_with_loading_message() {
+        let gateway = RecordingGateway::new();
+        let response = run_command(&gateway, "leaderboard").await;
+
+        assert_eq!(
+            response.response_type,
+            Some(SlackMessageResponseType::Ephemeral)
+        );
+        assert_eq!(
+            response.content.text.as_deref(),
+            Some(FALLBACK_LOADING_MESSAGE)
+        );
+        let posted = gateway.wait_for("chat.postEphemeral").await;
+        assert!(posted["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("The leaderboard hasn't been computed yet"));
+        assert_eq!(gateway.endpoints(), ["chat.postEphemeral"]);
+    }
+
+    #[tokio::test]
+    async fn unknown_subcommand_suggests_the_closest_one() {
+        let gateway = RecordingGateway::new();
+        let response = run_command(&gateway, "stast").await;
+
+        assert_eq!(
+            response.response_type,
+            Some(SlackMessageResponseType::Ephemeral)
+        );
+        assert_eq!(
+            response.content.text.as_deref(),
+            Some("Unknown command `stast`, did you mean `stats`?")
+        );
+        assert!(gateway.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn export_is_only_allowed_for_admins() {
+        let gateway = RecordingGateway::new();
+        let response = run_command(&gateway, "export").await;
+
+        assert_eq!(
+            response.content.text.as_deref(),
+            Some("Only admins can export all entries.")
+        );
+        assert!(gateway.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn bare_command_responds_with_usage() {
+        let gateway = RecordingGateway::new();
+        let response = run_command(&gateway, "").await;
+
+        assert_eq!(response.response_type, None);
+        let text = response.content.text.unwrap();
+        assert!(text.starts_with("*Usage*\n"), "{text}");
+        assert!(text.contains("• `/woss record` – Opens the form to record your hours"));
+        assert!(text.contains("• `/woss fix <url> <hours>` – "));
+        // Every subcommand is listed
+        assert_eq!(text.matches("• `/woss ").count(), SUBCOMMANDS.len());
+        assert!(gateway.calls().is_empty());
+    }
+
+    #[test]
+    fn usage_lists_aliases() {
+        let aliases = HashMap::from([
+            ("log".to_string(), "record".to_string()),
+            ("add".to_string(), "record".to_string()),
+        ]);
+
+        let text = usage_text(&aliases);
+        assert!(text.ends_with("\nAliases: `add` for `record`, `log` for `record`"));
+    }
+
+    #[tokio::test]
+    async fn confirmed_view_submission_posts_the_entry() {
+        let gateway = RecordingGateway::new().with_username(USER_ID, "jane");
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        let config = AppConfig::for_tests();
+        let event = view_submission(&slack::ModalMetadata {
+            edited_message: None,
+            confirmed_input: Some(modal_input()),
+        });
+
+        interaction_event_handler(Extension(event), Extension(state), Extension(config))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            gateway.endpoints(),
+            ["users.info", "chat.postMessage", "reactions.add"]
+        );
+        let posted = &gateway.requests("chat.postMessage")[0];
+        assert_eq!(posted["channel"], "COSS");
+        assert_eq!(
+            posted["attachments"][0]["fields"],
+            json!([
+                { "title": "Author", "value": "jane", "short": true },
+                { "title": "Time", "value": "1.5", "short": true },
+                { "title": "Office", "value": "germany", "short": true },
+                {
+                    "title": "URL",
+                    "value": "https://github.com/x3ro/wizard-of-oss/pull/1",
+                    "short": true
+                },
+                { "title": "Description", "value": "Fixed the build", "short": false },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_submission_is_only_posted_once() {
+        let gateway = RecordingGateway::new().with_username(USER_ID, "jane");
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        let metadata = slack::ModalMetadata {
+            edited_message: None,
+            confirmed_input: Some(modal_input()),
+        };
+
+        for _ in 0..2 {
+            interaction_event_handler(
+                Extension(view_submission(&metadata)),
+                Extension(state.clone()),
+                Extension(AppConfig::for_tests()),
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(gateway.requests("chat.postMessage").len(), 1);
+        let notice = &gateway.requests("chat.postEphemeral")[0];
+        assert_eq!(notice["user"], USER_ID);
+        assert_eq!(notice["text"], "This contribution was already recorded.");
+    }
+
+    #[tokio::test]
+    async fn failed_submission_can_be_retried() {
+        let gateway = RecordingGateway::new().with_username(USER_ID, "jane");
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        let metadata = slack::ModalMetadata {
+            edited_message: None,
+            confirmed_input: Some(modal_input()),
+        };
+        gateway.fail_next("chat.postMessage");
+
+        for _ in 0..2 {
+            interaction_event_handler(
+                Extension(view_submission(&metadata)),
+                Extension(state.clone()),
+                Extension(AppConfig::for_tests()),
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(gateway.requests("chat.postMessage").len(), 2);
+        assert!(gateway.requests("chat.postEphemeral").is_empty());
+        assert_eq!(
+            state
+                .persistence
+                .get_entries(None, None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn submission_fingerprint_is_stable() {
+        let attachment = OpenSourceAttachment {
+            username: "jane".to_string(),
+            number_of_hours: 1.5,
+            country: "germany".to_string(),
+            url: Url::parse("https://github.com/x3ro/wizard-of-oss/pull/1").unwrap(),
+            project: None,
+            description: "Fixed the build".to_string(),
+            tags: vec![],
+            co_authors: vec![],
+            submitted_at: Some(Utc.with_ymd_and_hms(2023, 1, 2, 10, 0, 0).unwrap()),
+        };
+        let user_id = SlackUserId(USER_ID.to_string());
+
+        // Fingerprints are stored, so changing them lets duplicates through after an update
+        assert_eq!(
+            submission_fingerprint(&user_id, &attachment),
+            "3ccc19b32677f1e4369c5dc0f93d70bbb10f754b527381e0e4bc75a4f55fd179"
+        );
+
+        let later = OpenSourceAttachment {
+            submitted_at: Some(Utc.with_ymd_and_hms(2023, 1, 3, 10, 0, 0).unwrap()),
+            ..attachment.clone()
+        };
+        assert_ne!(
+            submission_fingerprint(&user_id, &later),
+            submission_fingerprint(&user_id, &attachment)
+        );
+    }
+
+    #[test]
+    fn stats_range_is_parsed() {
+        let aliases = HashMap::new();
+        assert_eq!(
+            Subcommand::parse("stats since=2023-01-01 docs until=2023-03-31", &aliases),
+            Subcommand::Stats {
+                tag: Some("docs".to_string()),
+                user: None,
+                verbose: false,
+                since: Some("2023-01-01".to_string()),
+                until: Some("2023-03-31".to_string()),
+            }
+        );
+
+        let range = parse_entry_range(Some("2023-01-01"), Some("2023-03-31")).unwrap();
+        assert!(range.contains(Some(Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap())));
+        assert!(range.contains(Some(Utc.with_ymd_and_hms(2023, 3, 31, 23, 59, 59).unwrap())));
+        assert!(!range.contains(Some(Utc.with_ymd_and_hms(2023, 4, 1, 0, 0, 0).unwrap())));
+        assert_eq!(
+            parse_entry_range(None, None).unwrap(),
+            slack::EntryRange::default()
+        );
+
+        assert!(parse_entry_range(Some("2023-13-01"), None).is_err());
+        assert!(parse_entry_range(Some("yesterday"), None).is_err());
+        assert!(parse_entry_range(Some("2023-03-31"), Some("2023-01-01")).is_err());
+    }
+
+    #[tokio::test]
+    async fn stats_only_include_entries_in_range() {
+        let gateway = RecordingGateway::new();
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        let channel = SlackChannelId("COSS".to_string());
+        for (ts, hours, month) in [("1673344800.000000", 2.0, 1), ("1678442400.000000", 3.0, 3)] {
+            let entry = OpenSourceAttachment {
+                username: "jane".to_string(),
+                number_of_hours: hours,
+                country: "germany".to_string(),
+                url: Url::parse("https://github.com/x3ro/wizard-of-oss/pull/1").unwrap(),
+                project: None,
+                description: String::new(),
+                tags: vec![],
+                co_authors: vec![],
+                submitted_at: Some(Utc.with_ymd_and_hms(2023, month, 10, 10, 0, 0).unwrap()),
+            };
+            state
+                .persistence
+                .store_entry(&channel, &SlackTs(ts.to_string()), &entry)
+                .await
+                .unwrap();
+        }
+
+        let Json(response) = command_event_handler(
+            Extension(command("stats since=2023-02-01")),
+            Extension(state),
+            Extension(AppConfig::for_tests()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            response.content.text.as_deref(),
+            Some(FALLBACK_LOADING_MESSAGE)
+        );
+        let posted = gateway.wait_for("chat.postEphemeral").await;
+        assert!(posted["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("3 hours by 1 contributor in 1 entry"));
+        assert_eq!(posted["blocks"][0]["type"], "header");
+    }
+
+    #[tokio::test]
+    async fn invalid_stats_range_is_rejected() {
+        let gateway = RecordingGateway::new();
+        let response = run_command(&gateway, "stats since=01/02/2023").await;
+
+        assert_eq!(
+            response.response_type,
+            Some(SlackMessageResponseType::Ephemeral)
+        );
+        assert!(response
+            .content
+            .text
+            .unwrap()
+            .starts_with("Invalid day '01/02/2023'"));
+        assert!(gateway.calls().is_empty());
+    }
+
+    #[test]
+    fn hours_are_validated() {
+        let config = AppConfig::for_tests();
+        for (input, expected) in [("1h30m", 1.5), ("90m", 1.5), ("1.5", 1.5), ("1,5", 1.5)] {
+            assert_eq!(validate_hours(input, &config).unwrap(), expected, "{input}");
+        }
+
+        for input in ["-1", "0", "0:00", "0m", "garbage", "25"] {
+            assert!(validate_hours(input, &config).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        let public = |ip: &str| is_public_ip(ip.parse().unwrap());
+
+        assert!(public("140.82.121.4"));
+        assert!(public("2606:50c0:8000::153"));
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!public(ip), "{ip} shouldn't be public");
+        }
+    }
+
+    #[tokio::test]
+    async fn internal_urls_are_not_requested() {
+        for url in [
+            "http://127.0.0.1:6379/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]/",
+            "ftp://example.com/",
+        ] {
+            let res = check_url_reachable(&Url::parse(url).unwrap()).await;
+            assert!(
+                matches!(res, Err(AppError::InputValidationError { .. })),
+                "{url} shouldn't be requested"
+            );
+        }
+    }
+}
@@ -1,31 +1,96 @@
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
 
+use axum::middleware::{self, Next};
+use axum::response::Response;
 use axum::Extension;
+use http::Request;
+use hyper::client::HttpConnector;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
 use slack_morphism::prelude::*;
 use tracing::*;
 
+use crate::entries_cache::EntriesCache;
+use crate::gateway::{DryRunGateway, SlackApiGateway, SlackGateway};
 use crate::persistence::Persistence;
 use crate::request_handlers::{
-    command_event_handler, error_handler, install_cancel_handler, install_error_handler,
-    install_success_handler, interaction_event_handler, push_event_handler,
-    test_oauth_install_function,
+    admin_recompute_handler, command_event_handler, error_handler, health_handler,
+    install_cancel_handler, install_error_handler, install_success_handler,
+    interaction_event_handler, oauth_install_function, push_event_handler, readiness_handler,
 };
-use crate::{AppConfig, AppState};
+use crate::{countries, digest, loading_messages, slack, AppConfig, AppState};
+
+/// The same connector slack-morphism uses by default, but with a connect timeout, so
+/// that an unreachable Slack API doesn't tie up handlers
+fn https_connector(config: &AppConfig) -> HttpsConnector<HttpConnector> {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    http.set_connect_timeout(Some(config.slack_connect_timeout));
+
+    HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_only()
+        .enable_http1()
+        .wrap_connector(http)
+}
 
 pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    countries::validate()?;
+    slack::validate_modal()?;
+
+    // Keeps watching the loading messages file for as long as the server is running
+    let _loading_messages_watcher = match &config.loading_messages_path {
+        Some(path) => {
+            let path = Path::new(path);
+            if let Err(err) = loading_messages::load(path) {
+                warn!("{err:#}, using the default loading messages");
+            }
+            Some(loading_messages::watch(path)?)
+        }
+        None => None,
+    };
+
     let config_extension = Extension(config.clone());
     let token_value: SlackApiTokenValue = config.slack_test_token.clone().into();
     let api_token: SlackApiToken = SlackApiToken::new(token_value);
 
-    let client: Arc<SlackHyperClient> =
-        Arc::new(SlackClient::new(SlackClientHyperConnector::new()));
+    let client: Arc<SlackHyperClient> = Arc::new(SlackClient::new(
+        SlackClientHyperConnector::with_connector(https_connector(&config)),
+    ));
 
-    let app_state = AppState {
-        client: client.clone(),
+    let mut slack: Arc<dyn SlackGateway> = Arc::new(SlackApiGateway::new(
+        client.clone(),
         api_token,
-        persistence: Persistence::new(&config).await?,
+        config.slack_request_timeout,
+    ));
+    if config.dry_run {
+        warn!("Dry run is enabled, messages are only logged instead of posted to Slack");
+        slack = Arc::new(DryRunGateway::new(slack));
+    }
+
+    let persistence = Persistence::new(&config).await?;
+    let app_state = AppState {
+        slack,
+        persistence: persistence.clone(),
+        entries_cache: EntriesCache::new(),
+        started_at: Instant::now(),
     };
 
+    slack::join_oss_channel(&app_state, &config).await;
+    slack::check_oss_channel(&app_state, &config).await?;
+
+    if let Some(schedule) = config.digest_schedule.clone() {
+        if persistence.is_degraded(&config) {
+            // Without redis, neither the lock nor the last run are shared between
+            // instances and restarts, so the digest could be posted several times
+            warn!("Not scheduling the weekly digest, as redis isn't available");
+        } else {
+            info!("Scheduling the weekly digest: {schedule:?}");
+            tokio::spawn(digest::run(app_state.clone(), config.clone(), schedule));
+        }
+    }
+
     let addr = std::net::SocketAddr::from(([0, 0, 0, 0], config.port));
     info!("Starting server: {}", addr);
 
@@ -37,7 +102,10 @@ pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error +
     );
 
     let listener_environment: Arc<SlackHyperListenerEnvironment> = Arc::new(
-        SlackClientEventsListenerEnvironment::new(client.clone()).with_error_handler(error_handler),
+        SlackClientEventsListenerEnvironment::new(client.clone())
+            .with_error_handler(error_handler)
+            // Makes persistence available to the OAuth install function
+            .with_user_state(persistence),
     );
     let signing_secret: SlackSigningSecret = config.slack_signing_secret.into();
 
@@ -48,11 +116,17 @@ pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error +
     let app = axum::routing::Router::new()
         .nest(
             "/auth",
-            listener.oauth_router("/auth", &oauth_listener_config, test_oauth_install_function),
+            listener.oauth_router("/auth", &oauth_listener_config, oauth_install_function),
         )
         .route("/installed", axum::routing::get(install_success_handler))
         .route("/cancelled", axum::routing::get(install_cancel_handler))
         .route("/error", axum::routing::get(install_error_handler))
+        .route("/healthz", axum::routing::get(health_handler))
+        .route("/readyz", axum::routing::get(readiness_handler))
+        .route(
+            "/admin/recompute",
+            axum::routing::post(admin_recompute_handler),
+        )
         .route(
             "/push",
             axum::routing::post(push_event_handler).layer(
@@ -78,12 +152,63 @@ pub async fn start(config: AppConfig) -> Result<(), Box<dyn std::error::Error +
             ),
         )
         .layer(Extension(app_state))
-        .layer(config_extension);
+        .layer(config_extension)
+        .layer(middleware::from_fn(request_span));
 
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
-        .await
-        .unwrap();
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
 
     Ok(())
 }
+
+/// Wraps each request in a span with a request ID, so that all logs of a request can be
+/// correlated. Heroku's `X-Request-ID` header is used if present. The handlers add the
+/// user and command to the span once the Slack event has been parsed.
+async fn request_span<B>(req: Request<B>, next: Next<B>) -> Response {
+    let request_id = req
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+        .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()));
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        path = %req.uri().path(),
+        user_id = field::Empty,
+        command = field::Empty,
+    );
+
+    next.run(req).instrument(span).await
+}
+
+/// Resolves once the process receives SIGINT or SIGTERM, which lets the server finish
+/// in-flight requests before shutting down, e.g. during deploys.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install SIGINT handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("Shutting down");
+}
@@ -1,11 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
-use anyhow::{anyhow, format_err};
+use anyhow::{anyhow, bail, format_err, Context};
+use chrono::{DateTime, Datelike, FixedOffset, IsoWeek, Months, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use slack_morphism::errors::SlackClientError;
 use slack_morphism::prelude::*;
-use tracing::error;
+use tracing::{error, info, warn};
 
-use crate::models::OpenSourceAttachment;
-use crate::{AppConfig, AppState};
+use crate::digest::DigestMode;
+use crate::errors::AppError;
+use crate::models::{
+    display_hours, HoursAttribution, Leaderboard, OpenSourceAttachment, UniqueUrlCredit,
+};
+use crate::{countries, errors, models, AppConfig, AppState, ImageSize};
 
 fn cmp_block_id(block_id: &Option<SlackBlockId>, expected: impl AsRef<str>) -> bool {
     let expected = expected.as_ref();
@@ -31,101 +40,1821 @@ fn get_block(view: &mut SlackModalView, block_id: impl AsRef<str>) -> Option<&mu
 }
 
 const RECORD_HOURS_MODAL: &str = include_str!("../slack-ui/modal.json");
+
+/// Translations of the modal by language, e.g. `de`. The modal is shown in English for
+/// all other languages. The translations must have the same structure and IDs as the
+/// English modal, which is checked on startup.
+const LOCALIZED_MODALS: [(&str, &str); 1] = [("de", include_str!("../slack-ui/modal.de.json"))];
+
+/// The IDs of the modal blocks that the code relies on
+const REQUIRED_MODAL_BLOCKS: [&str; 4] = ["country", "number_of_hours", "url", "description"];
+
+/// Makes sure that the modal can be deserialized and contains all the blocks we rely on,
+/// so that a broken modal is caught on startup rather than when a user opens it.
+pub fn validate_modal() -> anyhow::Result<()> {
+    let mut modal = load_modal(None).context("Failed to deserialize the modal")?;
+
+    let missing: Vec<&str> = REQUIRED_MODAL_BLOCKS
+        .into_iter()
+        .filter(|block_id| get_block(&mut modal, block_id).is_none())
+        .collect();
+
+    if !missing.is_empty() {
+        bail!(
+            "The modal is missing blocks with the following IDs: {}",
+            missing.join(", ")
+        );
+    }
+
+    let expected_ids = modal_ids(RECORD_HOURS_MODAL)?;
+    for (language, json) in LOCALIZED_MODALS {
+        load_modal(Some(language))
+            .with_context(|| format!("Failed to deserialize the modal for '{language}'"))?;
+        if modal_ids(json)? != expected_ids {
+            bail!("The block or action IDs of the modal for '{language}' differ from the English modal");
+        }
+    }
+
+    Ok(())
+}
+
+/// The block and action IDs of the modal, as well as the values of options, in order
+fn modal_ids(json: &str) -> anyhow::Result<Vec<String>> {
+    fn collect(value: &serde_json::Value, ids: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    match (key.as_str(), value) {
+                        ("block_id" | "action_id" | "value", serde_json::Value::String(id)) => {
+                            ids.push(format!("{key}={id}"))
+                        }
+                        _ => collect(value, ids),
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => values.iter().for_each(|value| collect(value, ids)),
+            _ => {}
+        }
+    }
+
+    let mut ids = vec![];
+    collect(&serde_json::from_str(json)?, &mut ids);
+    Ok(ids)
+}
+
+/// Returns the language of the user's Slack locale, e.g. `de` for `de-DE`, which is used
+/// to show the modal in their language. Falls back to `None`, i.e. English, if the
+/// locale can't be looked up.
+pub async fn user_language(state: &AppState, user_id: &SlackUserId) -> Option<String> {
+    let req = SlackApiUsersInfoRequest {
+        user: user_id.clone(),
+        include_locale: Some(true),
+    };
+    let locale = match state.slack.users_info(&req).await {
+        Ok(res) => res.user.locale?,
+        Err(err) => {
+            warn!("Failed to look up the locale of user {}: {err}", user_id.0);
+            return None;
+        }
+    };
+
+    Some(locale_language(&locale.0))
+}
+
+/// The language part of a Slack locale, e.g. "de" for "de-DE"
+pub fn locale_language(locale: &str) -> String {
+    locale.split('-').next().unwrap_or(locale).to_lowercase()
+}
+
+/// Loads the modal in the given language, with the options of the country select filled
+/// in from the country list, see [`countries`]
+fn load_modal(language: Option<&str>) -> anyhow::Result<SlackModalView> {
+    let json = LOCALIZED_MODALS
+        .into_iter()
+        .find(|(modal_language, _)| Some(*modal_language) == language)
+        .map_or(RECORD_HOURS_MODAL, |(_, json)| json);
+    let mut modal: serde_json::Value = serde_json::from_str(json)?;
+
+    let options: Vec<serde_json::Value> = countries::all()
+        .iter()
+        .map(|country| {
+            json!({
+                "text": { "type": "plain_text", "text": country.label, "emoji": true },
+                "value": country.value,
+            })
+        })
+        .collect();
+
+    let country_block = modal["blocks"]
+        .as_array_mut()
+        .and_then(|blocks| {
+            blocks
+                .iter_mut()
+                .find(|block| block["block_id"] == "country")
+        })
+        .ok_or_else(|| anyhow!("The modal is missing the country block"))?;
+    country_block["element"]["options"] = json!(options);
+
+    Ok(serde_json::from_value(modal)?)
+}
+
+/// Loads the modal in the given language, adjusted to the configuration
+fn record_hours_modal(config: &AppConfig, language: Option<&str>) -> SlackModalView {
+    // The modal has been validated on startup, see `validate_modal`
+    let mut modal = load_modal(language).unwrap();
+
+    if !config.require_description {
+        match get_block(&mut modal, "description") {
+            Some(SlackBlock::Input(block)) => block.optional = Some(true),
+            _ => error!("Couldn't make the description optional, unexpected modal structure"),
+        }
+    }
+
+    modal
+}
+
 pub async fn open_oss_modal(
     state: &AppState,
+    config: &AppConfig,
     trigger_id: SlackTriggerId,
+    language: Option<&str>,
     default_country: Option<String>,
+    url: Option<String>,
+) -> anyhow::Result<()> {
+    let mut modal = record_hours_modal(config, language);
+
+    if let Some(default_country) = default_country {
+        set_initial_options(&mut modal, "country", &[default_country]);
+    }
+    if let Some(url) = url {
+        set_initial_value(&mut modal, "url", url);
+    }
+
+    let req = SlackApiViewsOpenRequest {
+        trigger_id,
+        view: SlackView::Modal(modal),
+    };
+
+    state
+        .slack
+        .views_open(&req)
+        .await
+        .map_err(AppError::slack_api("views.open"))?;
+
+    Ok(())
+}
+
+/// Reads a single message of a channel, e.g. one that was reacted to. Returns `None`
+/// if there is no such message, e.g. because it is a reply in a thread.
+pub async fn fetch_message(
+    state: &AppState,
+    channel: &SlackChannelId,
+    ts: &SlackTs,
+) -> anyhow::Result<Option<SlackHistoryMessage>> {
+    let req = SlackApiConversationsHistoryRequest {
+        channel: Some(channel.clone()),
+        cursor: None,
+        latest: Some(ts.clone()),
+        limit: Some(1),
+        oldest: None,
+        inclusive: Some(true),
+    };
+    let res = state
+        .slack
+        .conversations_history(&req)
+        .await
+        .map_err(AppError::slack_api("conversations.history"))?;
+
+    Ok(res
+        .messages
+        .into_iter()
+        .find(|message| &message.origin.ts == ts))
+}
+
+/// Replaces the modal with an empty one, keeping its metadata, e.g. which message is
+/// being edited. Slack keeps the values that were entered as long as the IDs of the input
+/// blocks stay the same, so they get a new suffix. Fields are looked up by their action
+/// ID for this reason, see [`SlackViewStateExt`].
+pub async fn clear_modal(
+    state: &AppState,
+    config: &AppConfig,
+    view_id: SlackViewId,
+    language: Option<&str>,
+    metadata: &ModalMetadata,
 ) -> anyhow::Result<()> {
-    // TODO: Would be nice if we caught it on startup if deserialization
-    //       of this view fails.
-    let mut modal: SlackModalView = serde_json::from_str(RECORD_HOURS_MODAL).unwrap();
+    let mut modal = record_hours_modal(config, language);
+
+    let suffix = format!("{:08x}", rand::random::<u32>());
+    for block in &mut modal.blocks {
+        if let SlackBlock::Input(SlackInputBlock {
+            block_id: Some(block_id),
+            ..
+        }) = block
+        {
+            *block_id = SlackBlockId(format!("{}-{suffix}", block_id.0));
+        }
+    }
+    let metadata = ModalMetadata {
+        edited_message: metadata.edited_message.clone(),
+        confirmed_input: None,
+    };
+    modal.private_metadata = Some(metadata.to_json()?);
+
+    let req = SlackApiViewsUpdateRequest {
+        view: SlackView::Modal(modal),
+        external_id: None,
+        hash: None,
+        view_id: Some(view_id),
+    };
+    state
+        .slack
+        .views_update(&req)
+        .await
+        .map_err(AppError::slack_api("views.update"))?;
+
+    Ok(())
+}
+
+/// Identifies the message of an entry that is being edited, so that the submission can
+/// update the message instead of posting a new one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditedMessage {
+    pub channel: SlackChannelId,
+    pub ts: SlackTs,
+    /// Usernames of the entry's co-authors, which are kept if no others are selected
+    #[serde(default)]
+    pub co_authors: Vec<String>,
+}
+
+/// The values the user entered into the modal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModalInput {
+    pub number_of_hours: String,
+    pub url: String,
+    pub description: String,
+    pub country: String,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+    /// User IDs of the selected co-authors
+    #[serde(default)]
+    pub co_authors: Vec<String>,
+}
+
+impl ModalInput {
+    pub fn from_view_state(view_state: &SlackViewState) -> anyhow::Result<Self> {
+        Ok(ModalInput {
+            number_of_hours: view_state.input_value("number_of_hours")?,
+            url: view_state.input_value("url")?,
+            // Only optional if configured so, which is checked when validating the input
+            description: view_state
+                .optional_input_value("description")
+                .unwrap_or_default(),
+            // Required, but checked when validating the input so that Slack highlights
+            // the field instead of failing the submission
+            country: view_state
+                .optional_select_value("country")
+                .unwrap_or_default(),
+            project: view_state.optional_input_value("project"),
+            tags: view_state.multi_select_values("tags"),
+            co_authors: view_state.multi_user_values("co_authors"),
+        })
+    }
+}
+
+/// State that is carried through the modal's private metadata as JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModalMetadata {
+    /// Set if an existing entry is being edited, see [`open_edit_modal`]
+    pub edited_message: Option<EditedMessage>,
+    /// Set once the user was shown a preview of the entry, see [`entry_preview_view`]
+    pub confirmed_input: Option<ModalInput>,
+}
+
+impl ModalMetadata {
+    pub fn from_view(view: &SlackView) -> Self {
+        let SlackView::Modal(SlackModalView {
+            private_metadata: Some(metadata),
+            ..
+        }) = view
+        else {
+            return ModalMetadata::default();
+        };
+
+        serde_json::from_str(metadata).unwrap_or_else(|err| {
+            warn!("Ignoring invalid private metadata of the modal: {err}");
+            ModalMetadata::default()
+        })
+    }
+
+    fn to_json(&self) -> anyhow::Result<String> {
+        serde_json::to_string(self).context("Failed to serialize the modal metadata")
+    }
+}
+
+/// Opens the modal prefilled with the values of an existing entry
+pub async fn open_edit_modal(
+    state: &AppState,
+    config: &AppConfig,
+    trigger_id: SlackTriggerId,
+    language: Option<&str>,
+    message: &EditedMessage,
+    entry: &OpenSourceAttachment,
+) -> anyhow::Result<()> {
+    let mut modal = record_hours_modal(config, language);
+
+    set_initial_value(
+        &mut modal,
+        "number_of_hours",
+        entry.number_of_hours.to_string(),
+    );
+    set_initial_value(&mut modal, "url", entry.url.to_string());
+    set_initial_value(&mut modal, "description", entry.description.clone());
+    if let Some(project) = &entry.project {
+        set_initial_value(&mut modal, "project", project.clone());
+    }
+    set_initial_options(&mut modal, "country", &[entry.country.clone()]);
+    set_initial_options(&mut modal, "tags", &entry.tags);
+    // Only the usernames of co-authors are stored, which can't be preselected
+    if !entry.co_authors.is_empty() {
+        match get_block(&mut modal, "co_authors") {
+            Some(SlackBlock::Input(block)) => {
+                let hint = format!(
+                    "Currently {}, leave empty to keep them",
+                    entry.co_authors.join(", ")
+                );
+                block.hint = Some(hint.into());
+            }
+            _ => error!("Couldn't show the current co-authors, unexpected modal structure"),
+        }
+    }
+    let metadata = ModalMetadata {
+        edited_message: Some(message.clone()),
+        confirmed_input: None,
+    };
+    modal.private_metadata = Some(metadata.to_json()?);
+
+    let req = SlackApiViewsOpenRequest {
+        trigger_id,
+        view: SlackView::Modal(modal),
+    };
+
+    state
+        .slack
+        .views_open(&req)
+        .await
+        .map_err(AppError::slack_api("views.open"))?;
+
+    Ok(())
+}
+
+/// Builds a read-only view of the entry, which replaces the modal after the first
+/// submission. The entry is only posted once the user submits this view as well.
+pub fn entry_preview_view(
+    input: &ModalInput,
+    number_of_hours: f64,
+    metadata: &ModalMetadata,
+) -> anyhow::Result<serde_json::Value> {
+    let mut lines = vec![
+        format!("*Time:* {} hours", display_hours(number_of_hours)),
+        format!("*Office:* {}", input.country),
+        format!("*URL:* {}", input.url),
+    ];
+    if let Some(project) = &input.project {
+        lines.push(format!("*Project:* {project}"));
+    }
+    if !input.tags.is_empty() {
+        lines.push(format!("*Tags:* {}", input.tags.join(", ")));
+    }
+    if !input.co_authors.is_empty() {
+        let mentions: Vec<String> = input
+            .co_authors
+            .iter()
+            .map(|id| format!("<@{id}>"))
+            .collect();
+        lines.push(format!("*Co-authors:* {}", mentions.join(", ")));
+    }
+    if !input.description.trim().is_empty() {
+        lines.push(format!("*Description:* {}", input.description));
+    }
+
+    let submit = match metadata.edited_message {
+        Some(_) => "Update",
+        None => "Post",
+    };
+
+    Ok(json!({
+        "type": "modal",
+        "title": {
+            "type": "plain_text",
+            "text": "Confirm your entry",
+        },
+        "submit": {
+            "type": "plain_text",
+            "text": submit,
+        },
+        "close": {
+            "type": "plain_text",
+            "text": "Cancel",
+        },
+        "private_metadata": metadata.to_json()?,
+        "blocks": [
+            {
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": "Please check your entry before it is posted.",
+                },
+            },
+            {
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": lines.join("\n"),
+                },
+            },
+        ],
+    }))
+}
+
+fn set_initial_value(modal: &mut SlackModalView, block_id: &str, value: String) {
+    match get_block(modal, block_id) {
+        Some(SlackBlock::Input(SlackInputBlock {
+            element: SlackInputBlockElement::PlainTextInput(element),
+            ..
+        })) => {
+            element.initial_value = Some(value);
+        }
+        _ => {
+            error!("Tried to set the initial value of `{block_id}` in the open source modal, but structure of the modal was not as expected");
+        }
+    }
+}
+
+/// Preselects the options with the given values, in either a single or multi select
+fn set_initial_options(modal: &mut SlackModalView, block_id: &str, values: &[String]) {
+    let preselected = |options: &Vec<SlackBlockChoiceItem<SlackBlockPlainTextOnly>>| {
+        options
+            .iter()
+            .filter(|opt| values.contains(&opt.value))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    match get_block(modal, block_id) {
+        Some(SlackBlock::Input(SlackInputBlock {
+            element:
+                SlackInputBlockElement::StaticSelect(SlackBlockStaticSelectElement {
+                    options: Some(options),
+                    initial_option,
+                    ..
+                }),
+            ..
+        })) => {
+            *initial_option = preselected(options).into_iter().next();
+        }
+        Some(SlackBlock::Input(SlackInputBlock {
+            element:
+                SlackInputBlockElement::MultiStaticSelect(SlackBlockMultiStaticSelectElement {
+                    options: Some(options),
+                    initial_options,
+                    ..
+                }),
+            ..
+        })) => {
+            let selected = preselected(options);
+            *initial_options = (!selected.is_empty()).then_some(selected);
+        }
+        _ => {
+            error!("Tried to set the default value for `{block_id}` in the open source modal, but structure of the modal was not as expected");
+        }
+    }
+}
+
+/// Slack timestamps are the seconds since the epoch, with a fractional part
+/// that makes them unique within a channel.
+pub fn ts_to_datetime(ts: &SlackTs) -> Option<DateTime<Utc>> {
+    let seconds = ts.0.split('.').next()?.parse::<i64>().ok()?;
+    Utc.timestamp_opt(seconds, 0).single()
+}
+
+/// Reads the contribution entries posted to the OSS channel, newest first, together
+/// with the timestamp of the message they were posted as. Messages that don't contain
+/// a valid contribution attachment are skipped. At most `HISTORY_MAX_PAGES` pages are
+/// read, to bound the time and number of API calls this takes for large channels. The
+/// returned flag tells whether older messages were left out because of that.
+///
+/// Stats are computed from the entries stored in redis instead, as Slack may delete
+/// old messages depending on the workspace's retention policy. Only in degraded mode,
+/// when redis wasn't reachable on startup, they are computed from the history.
+async fn fetch_channel_entries(
+    state: &AppState,
+    config: &AppConfig,
+) -> anyhow::Result<(Vec<(SlackTs, OpenSourceAttachment)>, bool)> {
+    let mut result = vec![];
+    let mut cursor = None;
+    let mut pages = 0;
+
+    loop {
+        let req = SlackApiConversationsHistoryRequest {
+            channel: Some(SlackChannelId(config.slack_oss_channel_id.clone())),
+            cursor,
+            latest: None,
+            limit: Some(200),
+            oldest: None,
+            inclusive: None,
+        };
+
+        let res = state
+            .slack
+            .conversations_history(&req)
+            .await
+            .map_err(AppError::slack_api("conversations.history"))?;
+
+        for x in &res.messages {
+            for entry in entries_from_message(x) {
+                result.push((x.origin.ts.clone(), entry));
+            }
+        }
+
+        // Slack sends an empty cursor along with the last page
+        cursor = res
+            .response_metadata
+            .and_then(|metadata| metadata.next_cursor)
+            .filter(|cursor| !cursor.0.is_empty());
+        if res.has_more != Some(true) || cursor.is_none() {
+            return Ok((result, false));
+        }
+
+        pages += 1;
+        if config.history_max_pages > 0 && pages >= config.history_max_pages {
+            warn!("Stopped reading the channel history after {pages} pages");
+            return Ok((result, true));
+        }
+    }
+}
+
+/// The period of time in which entries were submitted, see [`collect_entries`]. Both
+/// ends are optional, and inclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EntryRange {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl EntryRange {
+    pub fn since(date: DateTime<Utc>) -> Self {
+        EntryRange {
+            since: Some(date),
+            until: None,
+        }
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Entries without a submission date are only contained in unbounded ranges
+    pub fn contains(&self, submitted_at: Option<DateTime<Utc>>) -> bool {
+        match submitted_at {
+            Some(date) => {
+                self.since.map_or(true, |since| date >= since)
+                    && self.until.map_or(true, |until| date <= until)
+            }
+            None => self.is_unbounded(),
+        }
+    }
+}
+
+/// Reads all entries submitted in the given range, newest first. This is the common
+/// starting point of the stats, the digest, the export and the home tab.
+pub async fn collect_entries(
+    state: &AppState,
+    config: &AppConfig,
+    range: EntryRange,
+) -> anyhow::Result<Vec<OpenSourceAttachment>> {
+    if let Some(entries) = state.entries_cache.get(&range) {
+        return Ok(entries);
+    }
+
+    let entries = if state.persistence.is_degraded(config) {
+        // The in-memory store lacks everything recorded before the start, but the
+        // channel still has it, as far as the retention policy allows
+        let (entries, truncated) = fetch_channel_entries(state, config).await?;
+        if truncated {
+            warn!("The stats are incomplete, as the channel history was truncated");
+        }
+        entries.into_iter().map(|(_, entry)| entry).collect()
+    } else {
+        state
+            .persistence
+            .get_entries(range.since, range.until)
+            .await?
+    };
+    let entries: Vec<OpenSourceAttachment> = entries
+        .into_iter()
+        .filter(|entry| range.contains(entry.submitted_at))
+        .collect();
+    state.entries_cache.insert(range, entries.clone()).await;
+
+    Ok(entries)
+}
+
+/// Same as [`collect_entries`], but only the entries with the given tag, if any. If the
+/// entries can't be read, the user is told so and `None` is returned.
+async fn collect_stats_entries(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    range: EntryRange,
+    tag: Option<&str>,
+) -> Option<Vec<OpenSourceAttachment>> {
+    let entries = match collect_entries(state, config, range).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Failed to collect the entries for the stats: {err:?}");
+            let text = "Failed to compute the stats, please try again later.".to_string();
+            post_stats(state, config, event, text).await;
+            return None;
+        }
+    };
+
+    Some(
+        entries
+            .into_iter()
+            .filter(|entry| tag.map_or(true, |tag| entry.tags.iter().any(|t| t == tag)))
+            .collect(),
+    )
+}
+
+/// Copies the entries that are still present in the OSS channel into redis, so that
+/// entries posted before they were stored there are taken into account by the stats.
+pub async fn backfill_entries(
+    state: &AppState,
+    config: &AppConfig,
+) -> anyhow::Result<BackfillResult> {
+    let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+    let (entries, truncated) = fetch_channel_entries(state, config).await?;
+
+    let mut result = BackfillResult {
+        truncated,
+        ..BackfillResult::default()
+    };
+    for (ts, entry) in &entries {
+        if state
+            .persistence
+            .store_entry_if_missing(&channel, ts, entry)
+            .await?
+        {
+            result.imported += 1;
+        } else {
+            result.skipped += 1;
+        }
+    }
+    state.entries_cache.invalidate();
+
+    Ok(result)
+}
+
+/// The outcome of [`backfill_entries`]. Entries that are already stored are skipped, so
+/// that running the backfill again is safe.
+#[derive(Debug, Default)]
+pub struct BackfillResult {
+    pub imported: usize,
+    pub skipped: usize,
+    /// Whether older messages weren't read because of `HISTORY_MAX_PAGES`
+    pub truncated: bool,
+}
+
+/// Parses the contribution entries contained in the attachments of the given message.
+/// Attachments that aren't valid entries are skipped.
+pub fn entries_from_message(message: &SlackHistoryMessage) -> Vec<OpenSourceAttachment> {
+    let Some(attachments) = &message.content.attachments else {
+        return vec![];
+    };
+
+    let entries: Vec<anyhow::Result<OpenSourceAttachment>> = attachments
+        .iter()
+        .map(|x| x.fields.clone())
+        .map(|x| {
+            if let Some(fields) = x {
+                fields.try_into()
+            } else {
+                Err(format_err!("No attachments"))
+            }
+        })
+        .collect();
+
+    let submitted_at = ts_to_datetime(&message.origin.ts);
+    entries
+        .into_iter()
+        .flatten()
+        .map(|entry| OpenSourceAttachment {
+            submitted_at,
+            ..entry
+        })
+        .collect()
+}
+
+const TOP_PROJECTS_IN_STATS: usize = 5;
+
+/// Maximum number of entries listed by `/woss stats verbose` for all users, as the
+/// message would otherwise grow with the whole history of the channel
+const MAX_VERBOSE_ENTRIES: usize = 50;
+
+/// Extracts the user ID from an escaped user mention, e.g. `<@U123|name>`
+pub fn parse_user_mention(text: &str) -> Option<SlackUserId> {
+    let mention = text.strip_prefix("<@")?.strip_suffix('>')?;
+    let user_id = mention.split('|').next()?;
+    Some(SlackUserId(user_id.to_string()))
+}
+
+/// Reports the hours per user to the user that invoked the command. If a tag is
+/// given, only entries with that tag are taken into account. If a user is given,
+/// only the entries of that user are reported. Only entries submitted in the given
+/// range are taken into account.
+pub async fn report_user_stats(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    tag: Option<String>,
+    user: Option<SlackUserId>,
+    range: EntryRange,
+    verbose: bool,
+) {
+    let Some(entries) = collect_stats_entries(state, config, event, range, tag.as_deref()).await
+    else {
+        return;
+    };
+
+    let text = if let Some(user_id) = user {
+        // The goal is about the current quarter, which a range may not cover
+        let show_goal = range.is_unbounded();
+        single_user_stats(state, config, &entries, &user_id, show_goal, verbose).await
+    } else if entries.is_empty() && range.is_unbounded() {
+        "No contributions have been recorded yet.".to_string()
+    } else if entries.is_empty() {
+        "No contributions were recorded in this period.".to_string()
+    } else {
+        let content = all_users_stats(state, config, event, &entries, verbose).await;
+        post_stats_content(state, config, event, content).await;
+        return;
+    };
+
+    post_stats(state, config, event, text).await;
+}
+
+/// The stats of all users, with the totals in a header block above the breakdown by
+/// person, office and project
+async fn all_users_stats(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    entries: &[OpenSourceAttachment],
+    verbose: bool,
+) -> SlackMessageContent {
+    let by_user = hours_by_contributor(entries, config.co_author_hours);
+    let by_country = hours_by(entries, |entry| entry.country.clone());
+    let mut by_project = hours_by(entries, |entry| entry.project_name());
+    by_project.truncate(TOP_PROJECTS_IN_STATS);
+    let total_hours: f64 = entries.iter().map(|entry| entry.number_of_hours).sum();
+
+    let latest = match entries.iter().filter_map(|entry| entry.submitted_at).max() {
+        Some(date) => {
+            let timezone = user_timezone(state, &event.user_id).await;
+            let date = date.with_timezone(&timezone).format("%Y-%m-%d %H:%M");
+            format!(", the latest on {date}")
+        }
+        None => String::new(),
+    };
+
+    let details = if verbose {
+        let mut lines: Vec<String> = entries
+            .iter()
+            .take(MAX_VERBOSE_ENTRIES)
+            .map(|entry| {
+                let line = entry_details(entry, entry.number_of_hours, config);
+                format!("• {}: {line}", entry.username)
+            })
+            .collect();
+        if entries.len() > MAX_VERBOSE_ENTRIES {
+            let more = entries.len() - MAX_VERBOSE_ENTRIES;
+            lines.push(format!(
+                "…and {more} more, use a user, tag or range to narrow them down"
+            ));
+        }
+        format!("\n\n*Latest entries*\n{}", lines.join("\n"))
+    } else {
+        String::new()
+    };
+
+    let summary = format!(
+        "{} hours by {} in {}{latest}",
+        display_hours(total_hours),
+        plural(by_user.len(), "contributor", "contributors"),
+        plural(entries.len(), "entry", "entries"),
+    );
+    let breakdown = format!(
+        "*By person*\n{}\n\n*By office*\n{}\n\n*Top projects*\n{}{details}",
+        format_hours(&by_user),
+        format_hours(&by_country),
+        format_hours(&by_project)
+    );
+
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": summary }
+    })];
+    blocks.extend(text_sections(&breakdown));
+    let content = SlackMessageContent::new().with_text(format!("{summary}\n\n{breakdown}"));
+    match serde_json::from_value::<Vec<SlackBlock>>(json!(blocks)) {
+        Ok(blocks) => content.with_blocks(blocks),
+        Err(err) => {
+            // The text alone still contains everything
+            error!("Failed to build the blocks of the stats: {err}");
+            content
+        }
+    }
+}
+
+/// Slack rejects section blocks with more text than this
+const MAX_SECTION_TEXT_LENGTH: usize = 3000;
+
+/// Splits the text into section blocks at line breaks, so that long stats don't exceed
+/// `MAX_SECTION_TEXT_LENGTH`. Lines that are too long by themselves are cut off.
+fn text_sections(text: &str) -> Vec<serde_json::Value> {
+    let mut chunks: Vec<String> = vec![];
+    let mut current = String::new();
+    for line in text.lines() {
+        let line: String = line.chars().take(MAX_SECTION_TEXT_LENGTH).collect();
+        let length = current.chars().count() + line.chars().count() + 1;
+        if !current.is_empty() && length > MAX_SECTION_TEXT_LENGTH {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    chunks.push(current);
+
+    chunks
+        .into_iter()
+        .filter(|chunk| !chunk.trim().is_empty())
+        .map(|chunk| json!({ "type": "section", "text": { "type": "mrkdwn", "text": chunk } }))
+        .collect()
+}
+
+/// The count followed by the singular or plural noun, e.g. "1 entry" or "2 entries"
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    if count == 1 {
+        format!("{count} {singular}")
+    } else {
+        format!("{count} {plural}")
+    }
+}
+
+/// Listed in the team stats for entries of users without a team
+const UNASSIGNED_TEAM: &str = "Unassigned";
+
+/// Reports the hours per team to the user that invoked the command. Entries only contain
+/// usernames, so the user IDs of the configured teams are resolved first. If a tag is
+/// given, only entries with that tag are taken into account.
+pub async fn report_team_stats(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    tag: Option<String>,
+) {
+    if config.user_teams.is_empty() {
+        let text = "No teams are configured, see `USER_TEAMS`.".to_string();
+        post_stats(state, config, event, text).await;
+        return;
+    }
+
+    let mut team_by_username: HashMap<String, &str> = HashMap::new();
+    for (user_id, team) in &config.user_teams {
+        if let Some(username) = cached_username(state, &SlackUserId(user_id.clone())).await {
+            team_by_username.insert(username, team);
+        }
+    }
+
+    let Some(entries) =
+        collect_stats_entries(state, config, event, EntryRange::default(), tag.as_deref()).await
+    else {
+        return;
+    };
+
+    let text = if entries.is_empty() {
+        "No contributions have been recorded yet.".to_string()
+    } else {
+        // Co-authors are credited like in the per-person stats, see `HOURS_ATTRIBUTION`
+        let mut hours: HashMap<&str, f64> = HashMap::new();
+        for (username, credited) in hours_by_contributor(&entries, config.co_author_hours) {
+            let team = team_by_username
+                .get(&username)
+                .copied()
+                .unwrap_or(UNASSIGNED_TEAM);
+            *hours.entry(team).or_default() += credited;
+        }
+        let mut by_team: Vec<(String, f64)> = hours
+            .into_iter()
+            .map(|(team, hours)| (team.to_string(), hours))
+            .collect();
+        by_team.sort_by(|a, b| b.1.total_cmp(&a.1));
+        format!("*By team*\n{}", format_hours(&by_team))
+    };
+
+    post_stats(state, config, event, text).await;
+}
+
+/// Deletes the data stored about the user, see `Persistence::delete_user_data`, and
+/// returns a summary of what was removed for the admin who invoked `/woss forget`. The
+/// stored leaderboard is recomputed, as it contains the user's name and hours as well.
+pub async fn forget_user(
+    state: &AppState,
+    config: &AppConfig,
+    user_id: &SlackUserId,
+) -> anyhow::Result<String> {
+    let req = SlackApiUsersInfoRequest {
+        user: user_id.clone(),
+        include_locale: None,
+    };
+    // Without the username, only the settings stored by user ID can be deleted
+    let username = match state.slack.users_info(&req).await {
+        Ok(res) => res.user.name,
+        Err(err) => {
+            warn!("Failed to look up user {}: {err}", user_id.0);
+            None
+        }
+    };
+
+    let deleted = state
+        .persistence
+        .delete_user_data(user_id, username.as_deref())
+        .await?;
+    state.entries_cache.invalidate();
+
+    let settings = if deleted.settings.is_empty() {
+        "no settings".to_string()
+    } else {
+        deleted.settings.join(", ")
+    };
+    let mut text = format!(
+        "Removed the stored data of <@{}>: {settings}, {} entries, and their name from \
+         {} co-authored entries.",
+        user_id.0, deleted.entries, deleted.co_authored_entries
+    );
+    if username.is_none() {
+        text.push_str(" Their username couldn't be looked up, so entries were kept.");
+    }
+
+    match recompute_leaderboard(state, config).await {
+        Ok(_) => text.push_str(" The leaderboard was recomputed without them."),
+        Err(err) => {
+            warn!("Failed to recompute the leaderboard: {err:?}");
+            state.persistence.delete_leaderboard().await?;
+            text.push_str(
+                " The leaderboard couldn't be recomputed, so it was removed until the next \
+                 `/woss recompute`.",
+            );
+        }
+    }
+    text.push_str(" The messages in the channel were kept.");
+
+    Ok(text)
+}
+
+/// Number of weeks shown by [`report_trend`], including the current one
+const TREND_WEEKS: i64 = 12;
+
+/// Reports the hours per ISO week of the last few weeks to the user that invoked the
+/// command, to show the momentum rather than the totals. If a tag is given, only
+/// entries with that tag are taken into account.
+pub async fn report_trend(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    tag: Option<String>,
+) {
+    let today = Utc::now().date_naive();
+    let first_monday = today
+        - chrono::Duration::days(today.weekday().num_days_from_monday().into())
+        - chrono::Duration::weeks(TREND_WEEKS - 1);
+    let since = Utc.from_utc_datetime(&first_monday.and_hms_opt(0, 0, 0).unwrap());
+
+    let Some(entries) = collect_stats_entries(
+        state,
+        config,
+        event,
+        EntryRange::since(since),
+        tag.as_deref(),
+    )
+    .await
+    else {
+        return;
+    };
+
+    let mut hours_by_week: HashMap<IsoWeek, f64> = HashMap::new();
+    for entry in &entries {
+        if let Some(date) = entry.submitted_at {
+            *hours_by_week.entry(date.iso_week()).or_default() += entry.number_of_hours;
+        }
+    }
+
+    // Weeks without entries are included, so that gaps are visible
+    let weeks: Vec<(IsoWeek, f64)> = (0..TREND_WEEKS)
+        .map(|idx| {
+            let week = (first_monday + chrono::Duration::weeks(idx)).iso_week();
+            (week, hours_by_week.get(&week).copied().unwrap_or_default())
+        })
+        .collect();
+
+    let hours: Vec<f64> = weeks.iter().map(|(_, hours)| *hours).collect();
+    let lines: Vec<String> = weeks
+        .iter()
+        .map(|(week, hours)| {
+            let hours = display_hours(*hours);
+            format!("• {}-W{:02}: {hours} hours", week.year(), week.week())
+        })
+        .collect();
+
+    let text = format!(
+        "*Hours per week*\n{}\n\n{}",
+        sparkline(&hours),
+        lines.join("\n")
+    );
+
+    post_stats(state, config, event, text).await;
+}
+
+/// Computes the leaderboard of all entries and stores it, so that `/woss leaderboard`
+/// doesn't have to compute it every time
+pub async fn recompute_leaderboard(
+    state: &AppState,
+    config: &AppConfig,
+) -> anyhow::Result<Leaderboard> {
+    let entries = collect_entries(state, config, EntryRange::default()).await?;
+    let unique_entries = unique_by_url(&entries, config.unique_url_credit);
+    let leaderboard = Leaderboard {
+        computed_at: Utc::now(),
+        hours_by_user: hours_by_contributor(&entries, config.co_author_hours),
+        unique_hours_by_user: Some(hours_by_contributor(
+            &unique_entries,
+            config.co_author_hours,
+        )),
+    };
+    state.persistence.set_leaderboard(&leaderboard).await?;
+
+    Ok(leaderboard)
+}
+
+/// Reports the leaderboard stored by [`recompute_leaderboard`] to the user that invoked
+/// the command. If `unique` is set, URLs that were logged several times only count once.
+/// If an office is given, only its entries are ranked, see [`office_leaderboard`].
+pub async fn report_leaderboard(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    unique: bool,
+    office: Option<&countries::Country>,
+) {
+    if let Some(office) = office {
+        let text = office_leaderboard(state, config, unique, office).await;
+        post_stats(state, config, event, text).await;
+        return;
+    }
+
+    let leaderboard = state.persistence.get_leaderboard().await;
+    let (title, hours_by_user) = match &leaderboard {
+        Some(leaderboard) if unique => (
+            "Leaderboard of unique URLs",
+            leaderboard.unique_hours_by_user.as_ref(),
+        ),
+        Some(leaderboard) => ("Leaderboard", Some(&leaderboard.hours_by_user)),
+        None => ("Leaderboard", None),
+    };
+
+    let text = match (&leaderboard, hours_by_user) {
+        (_, Some(hours_by_user)) if hours_by_user.is_empty() => {
+            "No contributions have been recorded yet.".to_string()
+        }
+        (Some(leaderboard), Some(hours_by_user)) => {
+            let timezone = user_timezone(state, &event.user_id).await;
+            let computed_at = leaderboard
+                .computed_at
+                .with_timezone(&timezone)
+                .format("%Y-%m-%d %H:%M");
+            format!(
+                "*{title}* (as of {computed_at})\n{}",
+                format_ranking(hours_by_user)
+            )
+        }
+        _ => "The leaderboard hasn't been computed yet, please ask an admin to run \
+              `/woss recompute`."
+            .to_string(),
+    };
+
+    post_stats(state, config, event, text).await;
+}
+
+/// Slack error codes of `pins.add` meaning that the bot isn't allowed to pin messages
+const PIN_PERMISSION_ERRORS: [&str; 2] = ["missing_scope", "restricted_action"];
+
+/// Posts the leaderboard stored by [`recompute_leaderboard`] to the OSS channel and pins
+/// it, replacing the previously pinned one. Returns the text reported to the admin.
+pub async fn pin_leaderboard(state: &AppState, config: &AppConfig) -> anyhow::Result<String> {
+    let Some(leaderboard) = state.persistence.get_leaderboard().await else {
+        return Ok(
+            "The leaderboard hasn't been computed yet, please run `/woss recompute` \
+                   first."
+                .to_string(),
+        );
+    };
+
+    let text = format!(
+        "*Leaderboard* (as of {} UTC)\n{}",
+        leaderboard.computed_at.format("%Y-%m-%d %H:%M"),
+        format_ranking(&leaderboard.hours_by_user)
+    );
+    let channel = SlackChannelId(config.slack_oss_channel_id.clone());
+    let req = SlackApiChatPostMessageRequest {
+        channel: channel.clone(),
+        content: SlackMessageContent::new().with_text(text),
+        as_user: None,
+        icon_emoji: None,
+        icon_url: None,
+        link_names: None,
+        parse: None,
+        thread_ts: None,
+        username: None,
+        reply_broadcast: None,
+        unfurl_links: None,
+        unfurl_media: None,
+    };
+    let posted = state
+        .slack
+        .chat_post_message(&req)
+        .await
+        .map_err(AppError::slack_api("chat.postMessage"))?;
+
+    let pin_req = SlackApiPinsAddRequest {
+        channel: channel.clone(),
+        timestamp: posted.ts.clone(),
+    };
+    if let Err(err) = state.slack.pins_add(&pin_req).await {
+        return match &err {
+            SlackClientError::ApiError(api_err)
+                if PIN_PERMISSION_ERRORS.contains(&api_err.code.as_str()) =>
+            {
+                warn!("Not allowed to pin the leaderboard: {err}");
+                Ok(
+                    "Posted the leaderboard, but the bot isn't allowed to pin messages in the \
+                    channel. Please make sure it has the `pins:write` scope, or pin the \
+                    message manually."
+                        .to_string(),
+                )
+            }
+            _ => Err(AppError::slack_api("pins.add")(err).into()),
+        };
+    }
+
+    if let Some(previous) = state.persistence.get_pinned_leaderboard_ts().await {
+        let unpin_req = SlackApiPinsRemoveRequest {
+            channel,
+            timestamp: previous,
+        };
+        // E.g. the previous leaderboard was unpinned or deleted manually
+        if let Err(err) = state.slack.pins_remove(&unpin_req).await {
+            warn!("Failed to unpin the previous leaderboard: {err}");
+        }
+    }
+    state
+        .persistence
+        .set_pinned_leaderboard_ts(&posted.ts)
+        .await?;
+
+    Ok("Posted and pinned the leaderboard.".to_string())
+}
+
+/// Ranks the contributors of entries recorded for the given office. Unlike the overall
+/// leaderboard, this isn't stored, but computed from the cached entries on demand.
+async fn office_leaderboard(
+    state: &AppState,
+    config: &AppConfig,
+    unique: bool,
+    office: &countries::Country,
+) -> String {
+    let entries = match collect_entries(state, config, EntryRange::default()).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Failed to collect the entries for the leaderboard: {err:?}");
+            return "Failed to compute the leaderboard.".to_string();
+        }
+    };
+
+    let entries: Vec<OpenSourceAttachment> = entries
+        .into_iter()
+        .filter(|entry| entry.country.eq_ignore_ascii_case(&office.value))
+        .collect();
+    let (title, entries) = if unique {
+        (
+            "Leaderboard of unique URLs",
+            unique_by_url(&entries, config.unique_url_credit),
+        )
+    } else {
+        ("Leaderboard", entries)
+    };
+
+    let hours_by_user = hours_by_contributor(&entries, config.co_author_hours);
+    if hours_by_user.is_empty() {
+        return format!(
+            "No contributions have been recorded for {} yet.",
+            office.label
+        );
+    }
+
+    format!(
+        "*{title} of {}*\n{}",
+        office.label,
+        format_ranking(&hours_by_user)
+    )
+}
+
+/// Number of entries listed by `/woss recent` if no number is given
+pub const DEFAULT_RECENT_ENTRIES: usize = 10;
+
+/// Slack allows up to 50 blocks per message, one is needed for the header
+pub const MAX_RECENT_ENTRIES: usize = 40;
+
+/// Lists the latest entries of all users to the user that invoked the command
+pub async fn report_recent(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    count: usize,
+) {
+    let Some(entries) =
+        collect_stats_entries(state, config, event, EntryRange::default(), None).await
+    else {
+        return;
+    };
+
+    if entries.is_empty() {
+        let text = "No contributions have been recorded yet.".to_string();
+        post_stats(state, config, event, text).await;
+        return;
+    }
+
+    let count = count.min(entries.len());
+    let mut blocks = vec![json!({
+        "type": "section",
+        "text": { "type": "mrkdwn", "text": format!("*The latest {}*", plural(count, "entry", "entries")) }
+    })];
+    for entry in entries.iter().take(count) {
+        blocks.push(json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": format!(
+                    "*{}* – {} hours on {}\n{}",
+                    entry.username,
+                    display_hours(entry.number_of_hours),
+                    entry.project_name(),
+                    entry.url
+                )
+            }
+        }));
+    }
+
+    let blocks: Vec<SlackBlock> = match serde_json::from_value(json!(blocks)) {
+        Ok(blocks) => blocks,
+        Err(err) => {
+            error!("Failed to build the list of recent entries: {err}");
+            let text = "Failed to compute the stats, please try again later.".to_string();
+            post_stats(state, config, event, text).await;
+            return;
+        }
+    };
+    let content = SlackMessageContent::new()
+        .with_text(format!("The latest {}", plural(count, "entry", "entries")))
+        .with_blocks(blocks);
+
+    post_stats_content(state, config, event, content).await;
+}
+
+/// Renders the values as a line of unicode block characters, scaled to the maximum
+fn sparkline(values: &[f64]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let max = values.iter().copied().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max <= 0.0 {
+                return BLOCKS[0];
+            }
+            let idx = (value / max * (BLOCKS.len() - 1) as f64).round() as usize;
+            BLOCKS[idx.min(BLOCKS.len() - 1)]
+        })
+        .collect()
+}
+
+/// Posts the stats as a message that only the user who requested them can see
+async fn post_stats(state: &AppState, config: &AppConfig, event: &SlackCommandEvent, text: String) {
+    let content = SlackMessageContent::new().with_text(text);
+    post_stats_content(state, config, event, content).await;
+}
+
+/// Same as [`post_stats`], for stats that consist of blocks
+async fn post_stats_content(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    content: SlackMessageContent,
+) {
+    let req = SlackApiChatPostEphemeralRequest {
+        channel: SlackChannelId(config.slack_oss_channel_id.clone()),
+        user: event.user_id.clone(),
+        content,
+        as_user: None,
+        icon_emoji: None,
+        icon_url: None,
+        link_names: None,
+        parse: None,
+        thread_ts: None,
+        username: None,
+    };
+
+    match state.slack.chat_post_ephemeral(&req).await {
+        Ok(_) => {}
+        Err(err) if errors::is_channel_access_error(&err) => {
+            error!(
+                "Can't post the stats to the OSS channel {}, make sure that \
+                 SLACK_OSS_CHANNEL_ID is correct and the bot was invited: {err}",
+                config.slack_oss_channel_id
+            );
+            send_direct_message(state, &event.user_id, errors::MISCONFIGURED_MESSAGE).await;
+        }
+        Err(err) => errors::log_slack_error(&err),
+    }
+}
+
+/// Sends a message to the user through the bot's direct messages, which works
+/// independently of the channels the bot is a member of
+async fn send_direct_message(state: &AppState, user_id: &SlackUserId, text: &str) {
+    let req = SlackApiChatPostMessageRequest {
+        channel: SlackChannelId(user_id.0.clone()),
+        content: SlackMessageContent::new().with_text(text.into()),
+        as_user: None,
+        icon_emoji: None,
+        icon_url: None,
+        link_names: None,
+        parse: None,
+        thread_ts: None,
+        username: None,
+        reply_broadcast: None,
+        unfurl_links: None,
+        unfurl_media: None,
+    };
+    if let Err(err) = state.slack.chat_post_message(&req).await {
+        errors::log_slack_error(&err);
+    }
+}
+
+/// Joins the OSS channel if the bot isn't a member yet, and logs a clear error if the
+/// channel can't be accessed. Private channels can't be joined, the bot has to be
+/// invited to them instead.
+pub async fn join_oss_channel(state: &AppState, config: &AppConfig) {
+    let req = SlackApiConversationsJoinRequest {
+        channel: SlackChannelId(config.slack_oss_channel_id.clone()),
+    };
+
+    match state.slack.conversations_join(&req).await {
+        Ok(_) => info!("Joined the OSS channel {}", config.slack_oss_channel_id),
+        Err(SlackClientError::ApiError(err))
+            if err.code == "method_not_supported_for_channel_type" =>
+        {
+            warn!(
+                "The OSS channel {} is private or a direct message, make sure that the bot \
+                 was invited to it",
+                config.slack_oss_channel_id
+            );
+        }
+        Err(err) if errors::is_channel_access_error(&err) => {
+            error!(
+                "The OSS channel {} doesn't exist, make sure that SLACK_OSS_CHANNEL_ID is correct",
+                config.slack_oss_channel_id
+            );
+        }
+        Err(err) => errors::log_slack_error(&err),
+    }
+}
+
+/// Makes sure that the bot can read the history of the OSS channel, which the stats
+/// rely on. Reading the history requires a different scope depending on the type of
+/// the channel, so a missing scope is reported along with the scope that Slack expects.
+/// Other errors, e.g. if Slack isn't reachable, are only logged.
+pub async fn check_oss_channel(state: &AppState, config: &AppConfig) -> anyhow::Result<()> {
+    let req = SlackApiConversationsHistoryRequest {
+        channel: Some(SlackChannelId(config.slack_oss_channel_id.clone())),
+        cursor: None,
+        latest: None,
+        limit: Some(1),
+        oldest: None,
+        inclusive: None,
+    };
+
+    let Err(err) = state.slack.conversations_history(&req).await else {
+        return Ok(());
+    };
+
+    // Only `missing_scope` errors list the needed scope
+    if let Some(needed) = errors::response_body_field(&err, "needed") {
+        bail!(
+            "The bot can't read the OSS channel {}, it needs the scope '{needed}'. Public \
+             channels need channels:history, private channels groups:history and direct \
+             messages im:history, see SLACK_BOT_SCOPE.",
+            config.slack_oss_channel_id
+        )
+    }
+
+    if errors::is_channel_access_error(&err) {
+        bail!(
+            "The bot can't read the OSS channel {}, make sure that SLACK_OSS_CHANNEL_ID is \
+             correct and the bot was invited to the channel",
+            config.slack_oss_channel_id
+        )
+    }
+
+    warn!("Couldn't check access to the OSS channel: {err}");
+    Ok(())
+}
+
+/// Sums up the hours of a single user, and lists all of their entries, including the ones
+/// they are a co-author of
+async fn single_user_stats(
+    state: &AppState,
+    config: &AppConfig,
+    entries: &[OpenSourceAttachment],
+    user_id: &SlackUserId,
+    show_goal: bool,
+    verbose: bool,
+) -> String {
+    let req = SlackApiUsersInfoRequest {
+        user: user_id.clone(),
+        include_locale: None,
+    };
+    let username = match state.slack.users_info(&req).await {
+        Ok(res) => res.user.name,
+        Err(err) => {
+            warn!("Failed to look up user {}: {err}", user_id.0);
+            None
+        }
+    };
+    let Some(username) = username else {
+        return format!("Couldn't find the user <@{}>.", user_id.0);
+    };
 
-    let block = get_block(&mut modal, "country").expect("Modal is missing `country` field");
+    // The hours credited to the user per entry
+    let entries: Vec<(&OpenSourceAttachment, f64)> = entries
+        .iter()
+        .filter(|entry| entry.has_contributor(&username))
+        .map(|entry| {
+            let hours = entry.hours_credited_to(&username, config.co_author_hours);
+            (entry, hours)
+        })
+        .collect();
 
-    if let Some(default_country) = default_country {
-        match block {
-            SlackBlock::Input(SlackInputBlock {
-                element:
-                    SlackInputBlockElement::StaticSelect(
-                        SlackBlockStaticSelectElement {
-                            options: Some(options),
-                            initial_option,
-                            ..
-                        },
-                        ..,
-                    ),
-                ..
-            }) => {
-                let preselected = options.iter().find(|opt| opt.value == default_country);
-                *initial_option = preselected.cloned();
-            }
-            _ => {
-                error!("Tried to set the default value for the open source modal, but structure of the modal was not as expected");
-            }
+    let goal = match state.persistence.get_goal(user_id).await {
+        Some(goal) if show_goal => {
+            let since = quarter_start(Utc::now());
+            let quarter_hours: f64 = entries
+                .iter()
+                .filter(|(entry, _)| entry.submitted_at.map_or(false, |date| date >= since))
+                .map(|(_, hours)| hours)
+                .sum();
+            format!(
+                "\n\n*Goal this quarter*\n{}",
+                goal_progress(quarter_hours, goal)
+            )
         }
+        _ => String::new(),
+    };
+
+    if entries.is_empty() {
+        return format!("No entries found for <@{}>.{goal}", user_id.0);
     }
 
-    let req = SlackApiViewsOpenRequest {
-        trigger_id,
-        view: SlackView::Modal(modal),
+    let total_hours: f64 = entries.iter().map(|(_, hours)| hours).sum();
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|(entry, hours)| {
+            if verbose {
+                format!("• {}", entry_details(entry, *hours, config))
+            } else {
+                format!(
+                    "• {} hours on {}: {}",
+                    display_hours(*hours),
+                    entry.project_name(),
+                    entry.description
+                )
+            }
+        })
+        .collect();
+
+    format!(
+        "<@{}> recorded *{} hours* in {}{goal}\n\n{}",
+        user_id.0,
+        display_hours(total_hours),
+        plural(entries.len(), "entry", "entries"),
+        lines.join("\n")
+    )
+}
+
+/// Describes an entry for the verbose stats, with the project linked to the URL and the
+/// description shortened to `STATS_DESCRIPTION_LENGTH` characters
+fn entry_details(entry: &OpenSourceAttachment, hours: f64, config: &AppConfig) -> String {
+    let description = entry.description.trim();
+    let description = if description.chars().count() > config.stats_description_length {
+        let shortened: String = description
+            .chars()
+            .take(config.stats_description_length)
+            .collect();
+        format!("{}…", shortened.trim_end())
+    } else {
+        description.to_string()
     };
 
-    state.get_session().views_open(&req).await.unwrap();
+    format!(
+        "{} hours on <{}|{}>: {description}",
+        display_hours(hours),
+        entry.url,
+        entry.project_name()
+    )
+}
 
-    Ok(())
+/// Goals are set per quarter, see `/woss goal`. Returns the start of the quarter the
+/// given date is in.
+fn quarter_start(date: DateTime<Utc>) -> DateTime<Utc> {
+    let month = (date.month0() / 3) * 3 + 1;
+    Utc.with_ymd_and_hms(date.year(), month, 1, 0, 0, 0)
+        .unwrap()
 }
 
-pub async fn report_user_stats(state: &AppState, config: &AppConfig, event: &SlackCommandEvent) {
-    let req = SlackApiConversationsHistoryRequest {
-        channel: Some(SlackChannelId(config.slack_oss_channel_id.clone())),
-        cursor: None,
-        latest: None,
-        // TODO: Do we need to implement pagination here?
-        //       Take a look at how the current application handles it.
-        limit: Some(100),
-        oldest: None,
-        inclusive: None,
+/// Number of cells of the progress bar shown by [`goal_progress`]
+const PROGRESS_BAR_WIDTH: usize = 10;
+
+/// Describes how close the hours are to the goal, e.g. "6 of 20 hours (30%)", followed
+/// by a progress bar
+fn goal_progress(hours: f64, goal: f64) -> String {
+    let ratio = if goal > 0.0 { hours / goal } else { 1.0 };
+    let filled = ((ratio * PROGRESS_BAR_WIDTH as f64).round() as usize).min(PROGRESS_BAR_WIDTH);
+    let bar = format!(
+        "{}{}",
+        "█".repeat(filled),
+        "░".repeat(PROGRESS_BAR_WIDTH - filled)
+    );
+
+    format!(
+        "{} of {} hours ({:.0}%)\n`{bar}`",
+        display_hours(hours),
+        display_hours(goal),
+        ratio * 100.0
+    )
+}
+
+/// Offsets change with daylight saving time, so they are only cached for a day
+const USER_TZ_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long usernames looked up by [`cached_username`] are kept. Renames are rare, and
+/// only delay the assignment of entries with the new name.
+const USERNAME_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Returns the Slack username of the user, which is cached to avoid running into the
+/// rate limit of `users.info` when looking up many users
+async fn cached_username(state: &AppState, user_id: &SlackUserId) -> Option<String> {
+    if let Some(username) = state.persistence.get_username(user_id).await {
+        return Some(username);
+    }
+
+    let req = SlackApiUsersInfoRequest {
+        user: user_id.clone(),
+        include_locale: None,
+    };
+    let username = match state.slack.users_info(&req).await {
+        Ok(res) => res.user.name,
+        Err(err) => {
+            warn!("Failed to look up user {}: {err}", user_id.0);
+            return None;
+        }
+    };
+    let Some(username) = username else {
+        warn!(
+            "The user information of {} did not contain a username",
+            user_id.0
+        );
+        return None;
     };
 
     let res = state
-        .get_session()
-        .conversations_history(&req)
-        .await
-        .unwrap();
+        .persistence
+        .set_username(user_id, &username, USERNAME_CACHE_TTL)
+        .await;
+    if let Err(err) = res {
+        warn!("Failed to cache the username of {}: {err:?}", user_id.0);
+    }
 
-    let mut hours: HashMap<String, i16> = HashMap::new();
+    Some(username)
+}
 
-    for x in &res.messages {
-        let Some(attachments) = &x.content.attachments else {
-            continue;
-        };
+/// Returns the timezone the user configured in Slack, falling back to UTC
+async fn user_timezone(state: &AppState, user_id: &SlackUserId) -> FixedOffset {
+    let offset = match state.persistence.get_user_tz_offset(user_id).await {
+        Some(offset) => Some(offset),
+        None => fetch_user_tz_offset(state, user_id).await,
+    };
+
+    offset
+        .and_then(FixedOffset::east_opt)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+}
+
+async fn fetch_user_tz_offset(state: &AppState, user_id: &SlackUserId) -> Option<i32> {
+    let req = SlackApiUsersInfoRequest {
+        user: user_id.clone(),
+        include_locale: Some(true),
+    };
+    let offset = match state.slack.users_info(&req).await {
+        Ok(res) => res.user.tz_offset?,
+        Err(err) => {
+            warn!("Failed to look up the timezone of {}: {err}", user_id.0);
+            return None;
+        }
+    };
+
+    let res = state
+        .persistence
+        .set_user_tz_offset(user_id, offset, USER_TZ_CACHE_TTL)
+        .await;
+    if let Err(err) = res {
+        warn!("Failed to cache the timezone of {}: {err:?}", user_id.0);
+    }
+
+    Some(offset)
+}
+
+/// Picks the URL of the user's profile image in the preferred size. Returns `None` if
+/// the user has no profile image.
+pub fn profile_image_url(user: &SlackUser, size: ImageSize) -> Option<String> {
+    let resolutions = &user
+        .profile
+        .as_ref()?
+        .icon
+        .as_ref()?
+        .images
+        .as_ref()?
+        .resolutions;
+    let smallest = resolutions.iter().min_by_key(|(width, _)| *width);
+    let largest = resolutions.iter().max_by_key(|(width, _)| *width);
 
-        let entries: Vec<anyhow::Result<OpenSourceAttachment>> = attachments
+    let resolution = match size {
+        ImageSize::Smallest => smallest,
+        ImageSize::Largest => largest,
+        ImageSize::Exact(preferred) => resolutions
             .iter()
-            .map(|x| x.fields.clone())
-            .map(|x| {
-                if let Some(fields) = x {
-                    fields.try_into()
-                } else {
-                    Err(format_err!("No attachments"))
-                }
-            })
-            .collect();
+            .filter(|(width, _)| *width >= preferred)
+            .min_by_key(|(width, _)| *width)
+            .or(largest),
+    };
 
-        for entry in entries {
-            let Ok(entry) = entry else {
-                continue;
-            };
+    resolution.map(|(_, url)| url.clone())
+}
+
+/// Sums up the hours of the given entries grouped by the given key, e.g. the username.
+/// The groups are sorted by their hours in descending order.
+fn hours_by(
+    entries: &[OpenSourceAttachment],
+    key: impl Fn(&OpenSourceAttachment) -> String,
+) -> Vec<(String, f64)> {
+    let mut hours: HashMap<String, f64> = HashMap::new();
+
+    for entry in entries {
+        *hours.entry(key(entry)).or_default() += entry.number_of_hours;
+    }
+
+    let mut sorted: Vec<(String, f64)> = hours.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.total_cmp(&a.1));
+    sorted
+}
+
+/// Keeps one entry per normalized URL, so that a contribution that was logged several
+/// times, by the same or different users, is only counted once
+fn unique_by_url(
+    entries: &[OpenSourceAttachment],
+    credit: UniqueUrlCredit,
+) -> Vec<OpenSourceAttachment> {
+    let mut sorted: Vec<&OpenSourceAttachment> = entries.iter().collect();
+    // Entries without a submission date count as the earliest ones
+    sorted.sort_by_key(|entry| entry.submitted_at);
+    if credit == UniqueUrlCredit::Latest {
+        sorted.reverse();
+    }
+
+    let mut seen = HashSet::new();
+    sorted
+        .into_iter()
+        .filter(|entry| seen.insert(entry.normalized_url()))
+        .cloned()
+        .collect()
+}
+
+/// Sums up the hours credited to each contributor, sorted in descending order
+fn hours_by_contributor(
+    entries: &[OpenSourceAttachment],
+    attribution: HoursAttribution,
+) -> Vec<(String, f64)> {
+    let mut hours: HashMap<String, f64> = HashMap::new();
 
-            let current = hours.get(&entry.username).unwrap_or(&0);
-            hours.insert(entry.username.to_string(), current + entry.number_of_hours);
+    for entry in entries {
+        for (username, credited) in entry.credited_hours(attribution) {
+            *hours.entry(username.to_string()).or_default() += credited;
         }
     }
 
-    let req = SlackApiChatPostEphemeralRequest {
-        channel: SlackChannelId(config.slack_oss_channel_id.clone()),
-        user: event.user_id.clone(),
-        content: SlackMessageContent::new().with_text(format!("{:#?}", hours)),
+    let mut sorted: Vec<(String, f64)> = hours.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.total_cmp(&a.1));
+    sorted
+}
+
+/// Formats hours that are sorted in descending order as a numbered list
+fn format_ranking(hours: &[(String, f64)]) -> String {
+    hours
+        .iter()
+        .enumerate()
+        .map(|(idx, (name, hours))| {
+            format!("{}. {name} – {} hours", idx + 1, display_hours(*hours))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn format_hours(hours: &[(String, f64)]) -> String {
+    hours
+        .iter()
+        .map(|(name, hours)| format!("• {name}: {} hours", display_hours(*hours)))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Posts the leaderboard of the past week publicly to the given channel. In scoreboard
+/// mode, the previously posted scoreboard is updated instead of posting a new message.
+pub async fn post_weekly_digest(
+    state: &AppState,
+    config: &AppConfig,
+    channel_id: &str,
+    mode: DigestMode,
+) -> anyhow::Result<()> {
+    let one_week_ago = Utc::now() - chrono::Duration::weeks(1);
+    let entries = collect_entries(state, config, EntryRange::since(one_week_ago)).await?;
+
+    let leaderboard = hours_by_contributor(&entries, config.co_author_hours);
+
+    let text = if leaderboard.is_empty() {
+        "No open source contributions were recorded last week.".to_string()
+    } else {
+        format!(
+            "*Open source contributions of the last week*\n{}",
+            format_ranking(&leaderboard)
+        )
+    };
+
+    let channel = SlackChannelId(channel_id.to_string());
+    let content = SlackMessageContent::new().with_text(text);
+
+    let previous = match mode {
+        DigestMode::Scoreboard => state.persistence.get_scoreboard_ts().await,
+        DigestMode::Post => None,
+    };
+    if let Some(ts) = &previous {
+        let req = SlackApiChatUpdateRequest {
+            channel: channel.clone(),
+            content: content.clone(),
+            ts: ts.clone(),
+            as_user: None,
+            link_names: None,
+            parse: None,
+            reply_broadcast: None,
+        };
+        match state.slack.chat_update(&req).await {
+            Ok(_) => return Ok(()),
+            // E.g. the scoreboard was deleted, or the channel changed
+            Err(err) => warn!("Failed to update the scoreboard, posting a new one: {err}"),
+        }
+    }
+
+    let req = SlackApiChatPostMessageRequest {
+        channel: channel.clone(),
+        content,
         as_user: None,
         icon_emoji: None,
         icon_url: None,
@@ -133,14 +1862,317 @@ pub async fn report_user_stats(state: &AppState, config: &AppConfig, event: &Sla
         parse: None,
         thread_ts: None,
         username: None,
+        reply_broadcast: None,
+        unfurl_links: None,
+        unfurl_media: None,
+    };
+    let posted = state
+        .slack
+        .chat_post_message(&req)
+        .await
+        .map_err(AppError::slack_api("chat.postMessage"))?;
+
+    if mode == DigestMode::Scoreboard {
+        let pin_req = SlackApiPinsAddRequest {
+            channel: channel.clone(),
+            timestamp: posted.ts.clone(),
+        };
+        if let Err(err) = state.slack.pins_add(&pin_req).await {
+            warn!("Failed to pin the scoreboard: {err}");
+        }
+
+        // Otherwise the pins would pile up every time the scoreboard is replaced
+        if let Some(previous) = previous {
+            let unpin_req = SlackApiPinsRemoveRequest {
+                channel,
+                timestamp: previous,
+            };
+            // E.g. the previous scoreboard was deleted, or unpinned manually
+            if let Err(err) = state.slack.pins_remove(&unpin_req).await {
+                warn!("Failed to unpin the previous scoreboard: {err}");
+            }
+        }
+        state.persistence.set_scoreboard_ts(&posted.ts).await?;
+    }
+
+    Ok(())
+}
+
+/// Exports all entries as a CSV file, which is sent to the user that invoked the command
+pub async fn export_entries(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+) -> anyhow::Result<()> {
+    let entries = collect_entries(state, config, EntryRange::default()).await?;
+    let csv = models::entries_to_csv(&entries)?;
+
+    // Uploading to a user ID shares the file in the user's direct messages with the bot
+    let req = SlackApiFilesUploadRequest::new()
+        .with_channels(vec![SlackChannelId(event.user_id.0.clone())])
+        .with_content(csv)
+        .with_filename("oss-contributions.csv".to_string())
+        .with_title("Open source contributions".to_string());
+
+    state
+        .slack
+        .files_upload(&req)
+        .await
+        .map_err(AppError::slack_api("files.upload"))?;
+
+    Ok(())
+}
+
+/// Sends the entries of the given user, including the ones they are a co-author of, as
+/// JSON file to the user that invoked the command. The JSON has the same format as the
+/// entries sent to the webhook.
+pub async fn export_user_entries(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    user_id: &SlackUserId,
+) -> anyhow::Result<()> {
+    let req = SlackApiUsersInfoRequest {
+        user: user_id.clone(),
+        include_locale: None,
+    };
+    let res = state
+        .slack
+        .users_info(&req)
+        .await
+        .map_err(AppError::slack_api("users.info"))?;
+    let username = res
+        .user
+        .name
+        .context("The user information did not contain a username")?;
+
+    let entries: Vec<OpenSourceAttachment> = collect_entries(state, config, EntryRange::default())
+        .await?
+        .into_iter()
+        .filter(|entry| entry.has_contributor(&username))
+        .collect();
+    let json = serde_json::to_string_pretty(&entries)?;
+
+    let req = SlackApiFilesUploadRequest::new()
+        .with_channels(vec![SlackChannelId(event.user_id.0.clone())])
+        .with_content(json)
+        .with_filename(format!("oss-contributions-{username}.json"))
+        .with_title(format!("Open source contributions of {username}"));
+
+    state
+        .slack
+        .files_upload(&req)
+        .await
+        .map_err(AppError::slack_api("files.upload"))?;
+
+    Ok(())
+}
+
+/// Sends a summary of the entries submitted in the month starting on the given date as a
+/// markdown file to the user that invoked the command
+pub async fn send_monthly_report(
+    state: &AppState,
+    config: &AppConfig,
+    event: &SlackCommandEvent,
+    month: NaiveDate,
+) -> anyhow::Result<()> {
+    let next_month = month
+        .checked_add_months(Months::new(1))
+        .context("The month is out of range")?;
+    let range = EntryRange {
+        since: Some(Utc.from_utc_datetime(&month.and_hms_opt(0, 0, 0).unwrap())),
+        // Ranges are inclusive
+        until: Some(
+            Utc.from_utc_datetime(&next_month.and_hms_opt(0, 0, 0).unwrap())
+                - chrono::Duration::nanoseconds(1),
+        ),
+    };
+    let entries = collect_entries(state, config, range).await?;
+    let report = monthly_report(&month.format("%B %Y").to_string(), &entries, config);
+
+    let req = SlackApiFilesUploadRequest::new()
+        .with_channels(vec![SlackChannelId(event.user_id.0.clone())])
+        .with_content(report)
+        .with_filename(format!("oss-report-{}.md", month.format("%Y-%m")))
+        .with_title(format!("Open source report {}", month.format("%B %Y")));
+
+    state
+        .slack
+        .files_upload(&req)
+        .await
+        .map_err(AppError::slack_api("files.upload"))?;
+
+    Ok(())
+}
+
+/// Formats the totals of the entries by person, office and project as markdown
+fn monthly_report(month: &str, entries: &[OpenSourceAttachment], config: &AppConfig) -> String {
+    let mut report = format!("# Open source contributions in {month}\n\n");
+    if entries.is_empty() {
+        report.push_str("No contributions were recorded.\n");
+        return report;
+    }
+
+    let by_user = hours_by_contributor(entries, config.co_author_hours);
+    let total_hours: f64 = entries.iter().map(|entry| entry.number_of_hours).sum();
+    report.push_str(&format!(
+        "**{} hours** by {} in {}\n",
+        display_hours(total_hours),
+        plural(by_user.len(), "contributor", "contributors"),
+        plural(entries.len(), "entry", "entries")
+    ));
+
+    let sections = [
+        ("By person", "Person", by_user),
+        (
+            "By office",
+            "Office",
+            hours_by(entries, |entry| entry.country.clone()),
+        ),
+        (
+            "By project",
+            "Project",
+            hours_by(entries, |entry| entry.project_name()),
+        ),
+    ];
+    for (title, column, hours) in sections {
+        report.push_str(&format!(
+            "\n## {title}\n\n| {column} | Hours |\n|---|---:|\n"
+        ));
+        for (name, hours) in hours {
+            // Pipes would end the table cell
+            report.push_str(&format!(
+                "| {} | {} |\n",
+                name.replace('|', "\\|"),
+                display_hours(*hours)
+            ));
+        }
+    }
+
+    report
+}
+
+const RECENT_ENTRIES_ON_HOME_TAB: usize = 5;
+
+/// Publishes the App Home tab for the given user, showing a summary of
+/// their recorded contributions and a button to record new hours.
+/// See <https://api.slack.com/surfaces/tabs> for details.
+pub async fn publish_home_view(
+    state: &AppState,
+    config: &AppConfig,
+    user_id: SlackUserId,
+) -> anyhow::Result<()> {
+    let user_req = SlackApiUsersInfoRequest {
+        user: user_id.clone(),
+        include_locale: None,
+    };
+    let res = state
+        .slack
+        .users_info(&user_req)
+        .await
+        .map_err(AppError::slack_api("users.info"))?;
+    let Some(username) = res.user.name else {
+        return Err(anyhow!("The user information did not contain a username"));
+    };
+
+    let entries: Vec<OpenSourceAttachment> = collect_entries(state, config, EntryRange::default())
+        .await?
+        .into_iter()
+        .filter(|entry| entry.has_contributor(&username))
+        .collect();
+    let total_hours: f64 = entries
+        .iter()
+        .map(|entry| entry.hours_credited_to(&username, config.co_author_hours))
+        .sum();
+
+    let mut blocks = vec![
+        json!({
+            "type": "header",
+            "text": { "type": "plain_text", "text": "Your open source contributions" }
+        }),
+        json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": format!(
+                    "You have recorded *{} hours* in {} entries.",
+                    display_hours(total_hours),
+                    entries.len()
+                )
+            }
+        }),
+        json!({
+            "type": "actions",
+            "elements": [{
+                "type": "button",
+                "action_id": "record_oss_hours",
+                "style": "primary",
+                "text": { "type": "plain_text", "text": "Record hours" }
+            }]
+        }),
+        json!({ "type": "divider" }),
+    ];
+
+    if entries.is_empty() {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": "You haven't recorded any contributions yet." }
+        }));
+    }
+
+    for entry in entries.iter().take(RECENT_ENTRIES_ON_HOME_TAB) {
+        blocks.push(json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": format!(
+                    "*{} hours* – {}\n{}",
+                    display_hours(entry.number_of_hours),
+                    entry.url,
+                    entry.description
+                )
+            }
+        }));
+    }
+
+    let view: SlackView = serde_json::from_value(json!({ "type": "home", "blocks": blocks }))?;
+    let req = SlackApiViewsPublishRequest {
+        user_id,
+        view,
+        hash: None,
     };
+    state
+        .slack
+        .views_publish(&req)
+        .await
+        .map_err(AppError::slack_api("views.publish"))?;
 
-    state.get_session().chat_post_ephemeral(&req).await.unwrap();
+    Ok(())
+}
+
+/// Looks up the state of the field with the given action ID. The ID of the block the
+/// field is in may differ from the action ID, see [`clear_modal`].
+fn action_state<'a>(
+    view_state: &'a SlackViewState,
+    action_id: &str,
+) -> Option<&'a SlackViewStateValue> {
+    view_state
+        .values
+        .values()
+        .find_map(|actions| actions.get(&action_id.into()))
 }
 
+/// Extracts the values of fields from the state of a submitted view. Required fields
+/// result in an error naming the field if they are missing, while optional and multi
+/// select fields result in `None` or an empty list.
 pub trait SlackViewStateExt {
     fn input_value(&self, name: impl AsRef<str>) -> anyhow::Result<String>;
     fn select_value(&self, name: impl AsRef<str>) -> anyhow::Result<String>;
+    fn multi_select_values(&self, name: impl AsRef<str>) -> Vec<String>;
+    fn multi_user_values(&self, name: impl AsRef<str>) -> Vec<String>;
+    fn optional_input_value(&self, name: impl AsRef<str>) -> Option<String>;
+    fn optional_select_value(&self, name: impl AsRef<str>) -> Option<String>;
+    fn block_id(&self, name: impl AsRef<str>) -> Option<String>;
 }
 
 impl SlackViewStateExt for SlackViewState {
@@ -148,22 +2180,482 @@ impl SlackViewStateExt for SlackViewState {
     /// tries to extract the value for the field with the given name.
     /// See <https://api.slack.com/reference/interaction-payloads/views> for more details.
     fn input_value(&self, name: impl AsRef<str>) -> anyhow::Result<String> {
-        let id = name.as_ref();
-        self.values
-            .get(&id.into())
-            .and_then(|x| x.get(&id.into()))
+        action_state(self, name.as_ref())
             .and_then(|x| x.value.to_owned())
             .ok_or_else(|| anyhow!("Missing field '{}'", name.as_ref()))
     }
 
     /// Same as [`SlackViewStateExt::input_value`], but for a select field
     fn select_value(&self, name: impl AsRef<str>) -> anyhow::Result<String> {
-        let id = name.as_ref();
-        self.values
-            .get(&id.into())
-            .and_then(|x| x.get(&id.into()))
+        action_state(self, name.as_ref())
             .and_then(|x| x.selected_option.as_ref())
             .map(|x| x.value.clone())
-            .ok_or_else(|| anyhow!("Missing select '{}'", name.as_ref()))
+            .ok_or_else(|| anyhow!("Missing field '{}'", name.as_ref()))
+    }
+
+    /// Returns the values selected in a multi select field. Since selecting nothing is
+    /// valid for multi selects, a missing field results in an empty list.
+    fn multi_select_values(&self, name: impl AsRef<str>) -> Vec<String> {
+        action_state(self, name.as_ref())
+            .and_then(|x| x.selected_options.as_ref())
+            .map(|options| options.iter().map(|x| x.value.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the IDs of the users selected in a multi users select
+    fn multi_user_values(&self, name: impl AsRef<str>) -> Vec<String> {
+        action_state(self, name.as_ref())
+            .and_then(|x| x.selected_users.as_ref())
+            .map(|users| users.iter().map(|user| user.0.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Same as [`SlackViewStateExt::input_value`], but for optional fields. Returns `None`
+    /// if the field is missing or was left empty.
+    fn optional_input_value(&self, name: impl AsRef<str>) -> Option<String> {
+        self.input_value(name)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+    }
+
+    /// Same as [`SlackViewStateExt::select_value`], but for optional fields. Returns `None`
+    /// if nothing was selected.
+    fn optional_select_value(&self, name: impl AsRef<str>) -> Option<String> {
+        self.select_value(name).ok()
+    }
+
+    /// Returns the ID of the block that contains the field, which is needed to show
+    /// validation errors next to the field
+    fn block_id(&self, name: impl AsRef<str>) -> Option<String> {
+        let action_id: SlackActionId = name.as_ref().into();
+        self.values
+            .iter()
+            .find(|(_, actions)| actions.contains_key(&action_id))
+            .map(|(block_id, _)| block_id.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::gateway::mock::{RecordingGateway, POSTED_TS};
+
+    /// The state of a submitted modal, as sent by Slack. The block IDs are random, since
+    /// they change when the form is cleared.
+    fn filled_view_state() -> SlackViewState {
+        serde_json::from_value(json!({
+            "values": {
+                "a1": {
+                    "number_of_hours": { "type": "plain_text_input", "value": "1:30" }
+                },
+                "b2": {
+                    "url": {
+                        "type": "plain_text_input",
+                        "value": "https://github.com/x3ro/wizard-of-oss/pull/1"
+                    }
+                },
+                "c3": { "description": { "type": "plain_text_input", "value": "  " } },
+                "d4": {
+                    "country": {
+                        "type": "static_select",
+                        "selected_option": {
+                            "text": { "type": "plain_text", "text": "Germany" },
+                            "value": "germany"
+                        }
+                    }
+                },
+                "e5": {
+                    "tags": {
+                        "type": "multi_static_select",
+                        "selected_options": [
+                            { "text": { "type": "plain_text", "text": "Docs" }, "value": "docs" },
+                            { "text": { "type": "plain_text", "text": "Bugfix" }, "value": "bugfix" }
+                        ]
+                    }
+                },
+                "f6": {
+                    "co_authors": {
+                        "type": "multi_users_select",
+                        "selected_users": ["U0000000002", "U0000000003"]
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    /// Fields that are left empty are sent without a value, or not at all
+    fn empty_view_state() -> SlackViewState {
+        serde_json::from_value(json!({
+            "values": {
+                "a1": { "number_of_hours": { "type": "plain_text_input" } },
+                "d4": { "country": { "type": "static_select", "selected_option": null } }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn present_fields_are_extracted() {
+        let state = filled_view_state();
+
+        assert_eq!(state.input_value("number_of_hours").unwrap(), "1:30");
+        assert_eq!(state.select_value("country").unwrap(), "germany");
+        assert_eq!(state.optional_select_value("country").unwrap(), "germany");
+        assert_eq!(state.multi_select_values("tags"), ["docs", "bugfix"]);
+        assert_eq!(
+            state.multi_user_values("co_authors"),
+            ["U0000000002", "U0000000003"]
+        );
+        assert_eq!(state.block_id("url").as_deref(), Some("b2"));
+    }
+
+    #[test]
+    fn blank_optional_input_is_none() {
+        let state = filled_view_state();
+
+        assert_eq!(state.input_value("description").unwrap(), "  ");
+        assert_eq!(state.optional_input_value("description"), None);
+    }
+
+    #[test]
+    fn absent_fields_are_missing() {
+        let state = empty_view_state();
+
+        let err = state.input_value("number_of_hours").unwrap_err();
+        assert_eq!(err.to_string(), "Missing field 'number_of_hours'");
+        assert!(state.input_value("url").is_err());
+        assert!(state.select_value("country").is_err());
+        assert_eq!(state.optional_input_value("project"), None);
+        assert_eq!(state.optional_select_value("country"), None);
+        assert!(state.multi_select_values("tags").is_empty());
+        assert!(state.multi_user_values("co_authors").is_empty());
+        assert_eq!(state.block_id("url"), None);
+    }
+
+    #[test]
+    fn modal_input_is_read_from_view_state() {
+        let input = ModalInput::from_view_state(&filled_view_state()).unwrap();
+
+        assert_eq!(input.number_of_hours, "1:30");
+        assert_eq!(input.description, "");
+        assert_eq!(input.country, "germany");
+        assert_eq!(input.project, None);
+        assert_eq!(input.tags, ["docs", "bugfix"]);
+
+        let err = ModalInput::from_view_state(&empty_view_state()).unwrap_err();
+        assert_eq!(err.to_string(), "Missing field 'number_of_hours'");
+    }
+
+    /// A page of the channel history with a single entry
+    fn history_page(ts: &str, has_more: bool, next_cursor: &str) -> serde_json::Value {
+        json!({
+            "messages": [{
+                "type": "message",
+                "ts": ts,
+                "attachments": [{
+                    "fields": [
+                        { "title": "Author", "value": "jane", "short": true },
+                        { "title": "Time", "value": "2", "short": true },
+                        { "title": "Office", "value": "germany", "short": true },
+                        { "title": "URL", "value": "<https://example.com>", "short": true },
+                    ]
+                }]
+            }],
+            "has_more": has_more,
+            "response_metadata": { "next_cursor": next_cursor },
+        })
+    }
+
+    #[tokio::test]
+    async fn history_is_read_until_the_last_page() {
+        let gateway = RecordingGateway::new()
+            .with_history_page(history_page("1700000002.000000", true, "page-2"))
+            .with_history_page(history_page("1700000001.000000", false, ""))
+            .with_history_page(history_page("1700000000.000000", false, ""));
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+
+        let (entries, truncated) = fetch_channel_entries(&state, &AppConfig::for_tests())
+            .await
+            .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert!(!truncated);
+        let requests = gateway.requests("conversations.history");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1]["cursor"], "page-2");
+    }
+
+    /// A user as returned by `users.info`, with the profile images in the given widths
+    fn user_with_images(widths: &[u32]) -> SlackUser {
+        let images: serde_json::Map<_, _> = widths
+            .iter()
+            .map(|width| {
+                let url = format!("https://example.com/{width}.png");
+                (format!("image_{width}"), json!(url))
+            })
+            .collect();
+        serde_json::from_value(json!({ "id": "U0000000001", "profile": images })).unwrap()
+    }
+
+    #[test]
+    fn profile_image_is_picked_by_size() {
+        let user = user_with_images(&[72, 24, 512]);
+        let url = |size| profile_image_url(&user, size);
+
+        assert_eq!(
+            url(ImageSize::Smallest).unwrap(),
+            "https://example.com/24.png"
+        );
+        assert_eq!(
+            url(ImageSize::Largest).unwrap(),
+            "https://example.com/512.png"
+        );
+        assert_eq!(
+            url(ImageSize::Exact(72)).unwrap(),
+            "https://example.com/72.png"
+        );
+        // The next larger size is used if Slack doesn't provide the exact one
+        assert_eq!(
+            url(ImageSize::Exact(48)).unwrap(),
+            "https://example.com/72.png"
+        );
+        assert_eq!(
+            url(ImageSize::Exact(1024)).unwrap(),
+            "https://example.com/512.png"
+        );
+    }
+
+    #[test]
+    fn profile_image_is_missing_without_profile() {
+        let user: SlackUser = serde_json::from_value(json!({ "id": "U0000000001" })).unwrap();
+
+        assert_eq!(profile_image_url(&user, ImageSize::Largest), None);
+        assert_eq!(profile_image_url(&user, ImageSize::Exact(48)), None);
+    }
+
+    #[tokio::test]
+    async fn degraded_mode_reads_entries_from_history() {
+        let gateway =
+            RecordingGateway::new().with_history_page(history_page("1700000000.000000", false, ""));
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        // The in-memory store of the tests is in use although redis is configured
+        let config = AppConfig {
+            storage_backend: crate::store::StorageBackend::Redis,
+            ..AppConfig::for_tests()
+        };
+
+        let entries = collect_entries(&state, &config, EntryRange::default())
+            .await
+            .unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].username, "jane");
+        assert_eq!(gateway.requests("conversations.history").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn forgotten_user_is_removed_from_the_leaderboard() {
+        let gateway = RecordingGateway::new().with_username("U0000000001", "jane");
+        let state = AppState::for_tests(Arc::new(gateway));
+        let config = AppConfig::for_tests();
+        let entry = OpenSourceAttachment {
+            username: "jane".to_string(),
+            number_of_hours: 2.0,
+            country: "germany".to_string(),
+            url: "https://github.com/x3ro/wizard-of-oss/pull/1"
+                .parse()
+                .unwrap(),
+            project: None,
+            description: String::new(),
+            tags: vec![],
+            co_authors: vec![],
+            submitted_at: Some(Utc::now()),
+        };
+        let channel = SlackChannelId("COSS".to_string());
+        let ts = SlackTs("1700000000.000000".to_string());
+        state
+            .persistence
+            .store_entry(&channel, &ts, &entry)
+            .await
+            .unwrap();
+        recompute_leaderboard(&state, &config).await.unwrap();
+
+        let user_id = SlackUserId("U0000000001".to_string());
+        let text = forget_user(&state, &config, &user_id).await.unwrap();
+
+        assert!(state
+            .persistence
+            .get_entries(None, None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(text.contains("The leaderboard was recomputed without them."));
+        let leaderboard = state.persistence.get_leaderboard().await.unwrap();
+        assert!(leaderboard.hours_by_user.is_empty());
+    }
+
+    /// `/woss` invoked by the user U0000000001
+    fn command_event(text: &str) -> SlackCommandEvent {
+        serde_json::from_value(json!({
+            "team_id": "T0000000000",
+            "channel_id": "COSS",
+            "user_id": "U0000000001",
+            "command": "/woss",
+            "text": text,
+            "response_url": "https://hooks.slack.com/commands/T0000000000/1",
+            "trigger_id": "1.2.3",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn team_stats_credit_co_authors_and_cache_usernames() {
+        let gateway = RecordingGateway::new()
+            .with_username("U0000000001", "jane")
+            .with_username("U0000000002", "john");
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        let config = AppConfig {
+            user_teams: HashMap::from([
+                ("U0000000001".to_string(), "Core".to_string()),
+                ("U0000000002".to_string(), "Docs".to_string()),
+            ]),
+            ..AppConfig::for_tests()
+        };
+        let entry = OpenSourceAttachment {
+            username: "jane".to_string(),
+            number_of_hours: 2.0,
+            country: "germany".to_string(),
+            url: "https://github.com/x3ro/wizard-of-oss/pull/1"
+                .parse()
+                .unwrap(),
+            project: None,
+            description: String::new(),
+            tags: vec![],
+            co_authors: vec!["john".to_string()],
+            submitted_at: Some(Utc::now()),
+        };
+        let channel = SlackChannelId("COSS".to_string());
+        let ts = SlackTs("1700000000.000000".to_string());
+        state
+            .persistence
+            .store_entry(&channel, &ts, &entry)
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            report_team_stats(&state, &config, &command_event("teams"), None).await;
+        }
+
+        let posted = gateway.requests("chat.postEphemeral");
+        let text = posted[1]["text"].as_str().unwrap();
+        assert!(text.contains("• Core: 2 hours"), "{text}");
+        assert!(text.contains("• Docs: 2 hours"), "{text}");
+        // The second invocation uses the cached usernames
+        assert_eq!(gateway.requests("users.info").len(), 2);
+    }
+
+    #[test]
+    fn counts_are_pluralized() {
+        assert_eq!(plural(1, "entry", "entries"), "1 entry");
+        assert_eq!(plural(0, "entry", "entries"), "0 entries");
+        assert_eq!(plural(2, "contributor", "contributors"), "2 contributors");
+    }
+
+    #[test]
+    fn long_text_is_split_into_sections() {
+        let line = "x".repeat(1000);
+        let text = [line.as_str(); 5].join("\n");
+
+        let sections = text_sections(&text);
+
+        assert_eq!(sections.len(), 3);
+        for section in &sections {
+            let text = section["text"]["text"].as_str().unwrap();
+            assert!(text.len() <= MAX_SECTION_TEXT_LENGTH);
+        }
+        assert_eq!(sections[0]["text"]["text"], [line.as_str(); 2].join("\n"));
+    }
+
+    #[tokio::test]
+    async fn replaced_scoreboard_is_unpinned() {
+        let gateway = RecordingGateway::new();
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        let previous = SlackTs("1690000000.000000".to_string());
+        state
+            .persistence
+            .set_scoreboard_ts(&previous)
+            .await
+            .unwrap();
+        // E.g. the previous scoreboard was deleted
+        gateway.fail_next("chat.update");
+
+        post_weekly_digest(
+            &state,
+            &AppConfig::for_tests(),
+            "CDIGEST",
+            DigestMode::Scoreboard,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            gateway.endpoints(),
+            ["chat.update", "chat.postMessage", "pins.add", "pins.remove"]
+        );
+        assert_eq!(gateway.requests("pins.add")[0]["timestamp"], POSTED_TS);
+        assert_eq!(gateway.requests("pins.remove")[0]["timestamp"], previous.0);
+        assert_eq!(
+            state.persistence.get_scoreboard_ts().await.unwrap().0,
+            POSTED_TS
+        );
+    }
+
+    #[tokio::test]
+    async fn verbose_stats_list_a_limited_number_of_entries() {
+        let gateway = RecordingGateway::new();
+        let state = AppState::for_tests(Arc::new(gateway.clone()));
+        let config = AppConfig::for_tests();
+        let channel = SlackChannelId("COSS".to_string());
+        for idx in 0..=MAX_VERBOSE_ENTRIES {
+            let entry = OpenSourceAttachment {
+                username: "jane".to_string(),
+                number_of_hours: 1.0,
+                country: "germany".to_string(),
+                url: "https://github.com/x3ro/wizard-of-oss/pull/1"
+                    .parse()
+                    .unwrap(),
+                project: None,
+                description: format!("Entry {idx}"),
+                tags: vec![],
+                co_authors: vec![],
+                submitted_at: Some(Utc::now()),
+            };
+            let ts = SlackTs(format!("1700000000.{idx:06}"));
+            state
+                .persistence
+                .store_entry(&channel, &ts, &entry)
+                .await
+                .unwrap();
+        }
+
+        let event = command_event("stats verbose");
+        report_user_stats(
+            &state,
+            &config,
+            &event,
+            None,
+            None,
+            EntryRange::default(),
+            true,
+        )
+        .await;
+
+        let posted = gateway.requests("chat.postEphemeral");
+        let text = posted[0]["text"].as_str().unwrap();
+        assert_eq!(text.matches("• jane: ").count(), MAX_VERBOSE_ENTRIES);
+        assert!(text.contains("…and 1 more"), "{text}");
     }
 }
@@ -0,0 +1,578 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use redis::{ErrorKind, FromRedisValue, RedisError};
+
+use crate::errors::AppError;
+
+/// Where [`crate::persistence::Persistence`] keeps its data
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StorageBackend {
+    Redis,
+    /// Keeps everything in memory, which is lost when the bot stops. Meant for local
+    /// development and tests.
+    Memory,
+}
+
+/// The storage operations used by [`crate::persistence::Persistence`]. They mirror the
+/// redis commands the bot was originally built on: plain values, hashes and sets. Keys
+/// are used as they are, namespacing is up to the caller.
+#[async_trait]
+pub trait Store: Debug + Send + Sync {
+    /// Checks whether the store is reachable
+    async fn ping(&self) -> Result<(), AppError>;
+
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError>;
+
+    /// Sets the value. If a TTL is given, the key expires after that time.
+    async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), AppError>;
+
+    /// Same as [`Store::set`], but only if the key doesn't exist yet. Returns whether the
+    /// value was set.
+    async fn set_if_missing(&self, key: &str, value: &str, ttl: Duration)
+        -> Result<bool, AppError>;
+
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+
+    /// Deletes the key only if it still has the given value
+    async fn delete_if_equal(&self, key: &str, value: &str) -> Result<(), AppError>;
+
+    async fn hash_set(&self, key: &str, field: &str, value: &str) -> Result<(), AppError>;
+
+    /// Same as [`Store::hash_set`], but only if the field doesn't exist yet. Returns
+    /// whether the value was set.
+    async fn hash_set_if_missing(
+        &self,
+        key: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<bool, AppError>;
+
+    async fn hash_get_all(&self, key: &str) -> Result<Vec<(String, String)>, AppError>;
+
+    /// Returns the values of the given fields, in the same order. Missing fields are
+    /// `None`.
+    async fn hash_get_many(
+        &self,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<String>>, AppError>;
+
+    async fn hash_delete(&self, key: &str, field: &str) -> Result<(), AppError>;
+
+    async fn set_add(&self, key: &str, member: &str) -> Result<(), AppError>;
+
+    async fn set_remove(&self, key: &str, member: &str) -> Result<(), AppError>;
+
+    async fn set_members(&self, key: &str) -> Result<HashSet<String>, AppError>;
+
+    /// Adds the member to the sorted set, or updates its score if it's already there
+    async fn sorted_set_add(&self, key: &str, member: &str, score: i64) -> Result<(), AppError>;
+
+    async fn sorted_set_remove(&self, key: &str, member: &str) -> Result<(), AppError>;
+
+    /// Returns the members with a score in the given range, ordered by score. Both ends
+    /// are optional, and inclusive.
+    async fn sorted_set_range(
+        &self,
+        key: &str,
+        min: Option<i64>,
+        max: Option<i64>,
+    ) -> Result<Vec<String>, AppError>;
+}
+
+/// Opens the store of the given backend. For redis, the connection is checked.
+pub async fn open(backend: StorageBackend, redis_url: &str) -> anyhow::Result<Arc<dyn Store>> {
+    match backend {
+        StorageBackend::Redis => Ok(Arc::new(RedisStore::connect(redis_url).await?)),
+        StorageBackend::Memory => {
+            tracing::warn!("Using the in-memory store, all data is lost when the bot stops");
+            Ok(Arc::new(MemoryStore::default()))
+        }
+    }
+}
+
+/// How often a redis command is attempted before giving up, see [`RedisStore::query`]
+const MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, which is doubled for every further retry
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Whether the error is likely to go away by itself, e.g. while redis restarts
+fn is_transient(err: &RedisError) -> bool {
+    err.is_io_error()
+        || err.is_connection_refusal()
+        || err.is_connection_dropped()
+        || err.is_timeout()
+        || matches!(
+            err.kind(),
+            ErrorKind::BusyLoadingError | ErrorKind::TryAgain
+        )
+}
+
+/// Deletes a key only if it still has the given value, see [`Store::delete_if_equal`]
+const DELETE_IF_EQUAL_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end
+"#;
+
+#[derive(Debug)]
+pub struct RedisStore {
+    client: redis::Client,
+}
+
+impl RedisStore {
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+
+        // Make sure that we can connect to the redis instance before proceeding
+        client
+            .get_async_connection()
+            .await
+            .with_context(|| "Failed to start server, redis connection failed")?;
+
+        Ok(RedisStore { client })
+    }
+
+    /// Runs the command on a new connection. Transient errors, such as a refused
+    /// connection while redis restarts, are retried a few times with exponential backoff.
+    /// Only use this for commands that can safely be repeated.
+    async fn query<T: FromRedisValue>(&self, cmd: &redis::Cmd) -> Result<T, AppError> {
+        self.query_with_retries(cmd, true).await
+    }
+
+    /// Same as [`RedisStore::query`], but the command is sent at most once. This is
+    /// needed for the `NX` variants: If the response to the first attempt got lost, a
+    /// retry would find the key already set and report that it wasn't. Failing to
+    /// connect is still retried, as the command wasn't sent then.
+    async fn query_once<T: FromRedisValue>(&self, cmd: &redis::Cmd) -> Result<T, AppError> {
+        self.query_with_retries(cmd, false).await
+    }
+
+    async fn query_with_retries<T: FromRedisValue>(
+        &self,
+        cmd: &redis::Cmd,
+        repeatable: bool,
+    ) -> Result<T, AppError> {
+        let mut attempt = 1;
+        loop {
+            let (result, sent) = match self.client.get_async_connection().await {
+                Ok(mut conn) => (cmd.query_async(&mut conn).await, true),
+                Err(err) => (Err(err), false),
+            };
+
+            match result {
+                Ok(value) => return Ok(value),
+                Err(err)
+                    if attempt < MAX_ATTEMPTS && is_transient(&err) && (repeatable || !sent) =>
+                {
+                    let backoff = INITIAL_BACKOFF * 2u32.pow(attempt - 1);
+                    tracing::warn!(
+                        "Redis command failed (attempt {attempt}/{MAX_ATTEMPTS}), retrying in \
+                         {backoff:?}: {err}"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    tracing::error!("Redis command failed: {err}");
+                    return Err(err.into());
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Store for RedisStore {
+    async fn ping(&self) -> Result<(), AppError> {
+        self.query::<String>(&redis::cmd("PING")).await.map(|_| ())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        self.query(redis::cmd("GET").arg(key)).await
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), AppError> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value);
+        if let Some(ttl) = ttl {
+            cmd.arg("PX").arg(ttl.as_millis() as u64);
+        }
+        self.query(&cmd).await
+    }
+
+    async fn set_if_missing(
+        &self,
+        key: &str,
+        value: &str,
+        ttl: Duration,
+    ) -> Result<bool, AppError> {
+        let inserted: Option<String> = self
+            .query_once(
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(ttl.as_millis() as u64),
+            )
+            .await?;
+
+        Ok(inserted.is_some())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.query(redis::cmd("DEL").arg(key)).await
+    }
+
+    async fn delete_if_equal(&self, key: &str, value: &str) -> Result<(), AppError> {
+        self.query(
+            redis::cmd("EVAL")
+                .arg(DELETE_IF_EQUAL_SCRIPT)
+                .arg(1)
+                .arg(key)
+                .arg(value),
+        )
+        .await
+    }
+
+    async fn hash_set(&self, key: &str, field: &str, value: &str) -> Result<(), AppError> {
+        self.query(redis::cmd("HSET").arg(key).arg(field).arg(value))
+            .await
+    }
+
+    async fn hash_set_if_missing(
+        &self,
+        key: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<bool, AppError> {
+        self.query_once(redis::cmd("HSETNX").arg(key).arg(field).arg(value))
+            .await
+    }
+
+    async fn hash_get_all(&self, key: &str) -> Result<Vec<(String, String)>, AppError> {
+        self.query(redis::cmd("HGETALL").arg(key)).await
+    }
+
+    async fn hash_get_many(
+        &self,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<String>>, AppError> {
+        // HMGET requires at least one field
+        if fields.is_empty() {
+            return Ok(vec![]);
+        }
+        self.query(redis::cmd("HMGET").arg(key).arg(fields)).await
+    }
+
+    async fn hash_delete(&self, key: &str, field: &str) -> Result<(), AppError> {
+        self.query(redis::cmd("HDEL").arg(key).arg(field)).await
+    }
+
+    async fn set_add(&self, key: &str, member: &str) -> Result<(), AppError> {
+        self.query(redis::cmd("SADD").arg(key).arg(member)).await
+    }
+
+    async fn set_remove(&self, key: &str, member: &str) -> Result<(), AppError> {
+        self.query(redis::cmd("SREM").arg(key).arg(member)).await
+    }
+
+    async fn set_members(&self, key: &str) -> Result<HashSet<String>, AppError> {
+        self.query(redis::cmd("SMEMBERS").arg(key)).await
+    }
+
+    async fn sorted_set_add(&self, key: &str, member: &str, score: i64) -> Result<(), AppError> {
+        self.query(redis::cmd("ZADD").arg(key).arg(score).arg(member))
+            .await
+    }
+
+    async fn sorted_set_remove(&self, key: &str, member: &str) -> Result<(), AppError> {
+        self.query(redis::cmd("ZREM").arg(key).arg(member)).await
+    }
+
+    async fn sorted_set_range(
+        &self,
+        key: &str,
+        min: Option<i64>,
+        max: Option<i64>,
+    ) -> Result<Vec<String>, AppError> {
+        let min = min.map_or("-inf".to_string(), |min| min.to_string());
+        let max = max.map_or("+inf".to_string(), |max| max.to_string());
+        self.query(redis::cmd("ZRANGEBYSCORE").arg(key).arg(min).arg(max))
+            .await
+    }
+}
+
+#[derive(Debug)]
+enum Value {
+    String(String),
+    Hash(HashMap<String, String>),
+    Set(HashSet<String>),
+    /// The score of every member
+    SortedSet(HashMap<String, i64>),
+}
+
+#[derive(Debug)]
+struct Item {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+/// Keeps everything in a map, see [`StorageBackend::Memory`]. Operations on a key of
+/// the wrong type fail like they do in redis.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    items: Mutex<HashMap<String, Item>>,
+}
+
+impl MemoryStore {
+    /// Runs the function on the items, after removing the expired ones
+    fn with_items<T>(&self, f: impl FnOnce(&mut HashMap<String, Item>) -> T) -> T {
+        let mut items = self.items.lock().unwrap();
+        let now = Instant::now();
+        items.retain(|_, item| item.expires_at.map_or(true, |expires_at| expires_at > now));
+        f(&mut items)
+    }
+}
+
+fn wrong_type(key: &str) -> AppError {
+    AppError::InternalServerError(anyhow!("The value of {key} has a different type"))
+}
+
+fn hash_mut<'a>(
+    items: &'a mut HashMap<String, Item>,
+    key: &str,
+) -> Result<&'a mut HashMap<String, String>, AppError> {
+    let item = items.entry(key.to_string()).or_insert_with(|| Item {
+        value: Value::Hash(HashMap::new()),
+        expires_at: None,
+    });
+    match &mut item.value {
+        Value::Hash(hash) => Ok(hash),
+        _ => Err(wrong_type(key)),
+    }
+}
+
+fn set_mut<'a>(
+    items: &'a mut HashMap<String, Item>,
+    key: &str,
+) -> Result<&'a mut HashSet<String>, AppError> {
+    let item = items.entry(key.to_string()).or_insert_with(|| Item {
+        value: Value::Set(HashSet::new()),
+        expires_at: None,
+    });
+    match &mut item.value {
+        Value::Set(set) => Ok(set),
+        _ => Err(wrong_type(key)),
+    }
+}
+
+fn sorted_set_mut<'a>(
+    items: &'a mut HashMap<String, Item>,
+    key: &str,
+) -> Result<&'a mut HashMap<String, i64>, AppError> {
+    let item = items.entry(key.to_string()).or_insert_with(|| Item {
+        value: Value::SortedSet(HashMap::new()),
+        expires_at: None,
+    });
+    match &mut item.value {
+        Value::SortedSet(set) => Ok(set),
+        _ => Err(wrong_type(key)),
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn ping(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        self.with_items(|items| match items.get(key).map(|item| &item.value) {
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(wrong_type(key)),
+            None => Ok(None),
+        })
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Option<Duration>) -> Result<(), AppError> {
+        self.with_items(|items| {
+            let item = Item {
+                value: Value::String(value.to_string()),
+                expires_at: ttl.map(|ttl| Instant::now() + ttl),
+            };
+            items.insert(key.to_string(), item);
+            Ok(())
+        })
+    }
+
+    async fn set_if_missing(
+        &self,
+        key: &str,
+        value: &str,
+        ttl: Duration,
+    ) -> Result<bool, AppError> {
+        self.with_items(|items| {
+            if items.contains_key(key) {
+                return Ok(false);
+            }
+            let item = Item {
+                value: Value::String(value.to_string()),
+                expires_at: Some(Instant::now() + ttl),
+            };
+            items.insert(key.to_string(), item);
+            Ok(true)
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.with_items(|items| {
+            items.remove(key);
+            Ok(())
+        })
+    }
+
+    async fn delete_if_equal(&self, key: &str, value: &str) -> Result<(), AppError> {
+        self.with_items(|items| {
+            if let Some(Value::String(current)) = items.get(key).map(|item| &item.value) {
+                if current == value {
+                    items.remove(key);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    async fn hash_set(&self, key: &str, field: &str, value: &str) -> Result<(), AppError> {
+        self.with_items(|items| {
+            hash_mut(items, key)?.insert(field.to_string(), value.to_string());
+            Ok(())
+        })
+    }
+
+    async fn hash_set_if_missing(
+        &self,
+        key: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<bool, AppError> {
+        self.with_items(|items| {
+            let hash = hash_mut(items, key)?;
+            if hash.contains_key(field) {
+                return Ok(false);
+            }
+            hash.insert(field.to_string(), value.to_string());
+            Ok(true)
+        })
+    }
+
+    async fn hash_get_all(&self, key: &str) -> Result<Vec<(String, String)>, AppError> {
+        self.with_items(|items| match items.get(key).map(|item| &item.value) {
+            Some(Value::Hash(hash)) => Ok(hash
+                .iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()),
+            Some(_) => Err(wrong_type(key)),
+            None => Ok(vec![]),
+        })
+    }
+
+    async fn hash_get_many(
+        &self,
+        key: &str,
+        fields: &[String],
+    ) -> Result<Vec<Option<String>>, AppError> {
+        self.with_items(|items| match items.get(key).map(|item| &item.value) {
+            Some(Value::Hash(hash)) => Ok(fields
+                .iter()
+                .map(|field| hash.get(field).cloned())
+                .collect()),
+            Some(_) => Err(wrong_type(key)),
+            None => Ok(vec![None; fields.len()]),
+        })
+    }
+
+    async fn hash_delete(&self, key: &str, field: &str) -> Result<(), AppError> {
+        self.with_items(
+            |items| match items.get_mut(key).map(|item| &mut item.value) {
+                Some(Value::Hash(hash)) => {
+                    hash.remove(field);
+                    Ok(())
+                }
+                Some(_) => Err(wrong_type(key)),
+                None => Ok(()),
+            },
+        )
+    }
+
+    async fn set_add(&self, key: &str, member: &str) -> Result<(), AppError> {
+        self.with_items(|items| {
+            set_mut(items, key)?.insert(member.to_string());
+            Ok(())
+        })
+    }
+
+    async fn set_remove(&self, key: &str, member: &str) -> Result<(), AppError> {
+        self.with_items(|items| {
+            set_mut(items, key)?.remove(member);
+            Ok(())
+        })
+    }
+
+    async fn set_members(&self, key: &str) -> Result<HashSet<String>, AppError> {
+        self.with_items(|items| match items.get(key).map(|item| &item.value) {
+            Some(Value::Set(set)) => Ok(set.clone()),
+            Some(_) => Err(wrong_type(key)),
+            None => Ok(HashSet::new()),
+        })
+    }
+
+    async fn sorted_set_add(&self, key: &str, member: &str, score: i64) -> Result<(), AppError> {
+        self.with_items(|items| {
+            sorted_set_mut(items, key)?.insert(member.to_string(), score);
+            Ok(())
+        })
+    }
+
+    async fn sorted_set_remove(&self, key: &str, member: &str) -> Result<(), AppError> {
+        self.with_items(|items| {
+            sorted_set_mut(items, key)?.remove(member);
+            Ok(())
+        })
+    }
+
+    async fn sorted_set_range(
+        &self,
+        key: &str,
+        min: Option<i64>,
+        max: Option<i64>,
+    ) -> Result<Vec<String>, AppError> {
+        self.with_items(|items| match items.get(key).map(|item| &item.value) {
+            Some(Value::SortedSet(set)) => {
+                let mut members: Vec<(&String, i64)> = set
+                    .iter()
+                    .map(|(member, score)| (member, *score))
+                    .filter(|(_, score)| min.map_or(true, |min| *score >= min))
+                    .filter(|(_, score)| max.map_or(true, |max| *score <= max))
+                    .collect();
+                // Like redis, members with the same score are ordered lexicographically
+                members.sort_by(|(a, a_score), (b, b_score)| a_score.cmp(b_score).then(a.cmp(b)));
+                Ok(members
+                    .into_iter()
+                    .map(|(member, _)| member.clone())
+                    .collect())
+            }
+            Some(_) => Err(wrong_type(key)),
+            None => Ok(vec![]),
+        })
+    }
+}
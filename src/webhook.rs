@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use hmac::{Hmac, Mac};
+use hyper::{Body, Client, Request};
+use sha2::Sha256;
+use tracing::{info, warn};
+
+use crate::models::OpenSourceAttachment;
+use crate::AppConfig;
+
+const MAX_ATTEMPTS: u32 = 3;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header containing the hex encoded HMAC-SHA256 of the body, computed with the
+/// configured secret, e.g. `sha256=1f2e...`
+const SIGNATURE_HEADER: &str = "X-Woss-Signature";
+
+/// Sends the new entry to the configured webhook, if any, so that external systems can
+/// react to contributions. Failed requests are retried a few times and then given up
+/// on, as the entry was already posted to Slack successfully.
+pub async fn notify(config: &AppConfig, entry: &OpenSourceAttachment) {
+    let Some(url) = &config.webhook_url else {
+        return;
+    };
+
+    if config.dry_run {
+        info!("Dry run, not sending the new entry to the webhook {url}");
+        return;
+    }
+
+    let body = match serde_json::to_vec(entry) {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("Failed to serialize the entry for the webhook: {err}");
+            return;
+        }
+    };
+    let signature = config
+        .webhook_secret
+        .as_ref()
+        .map(|secret| sign(secret, &body));
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        match send(url, &body, signature.as_deref()).await {
+            Ok(()) => {
+                info!("Sent the new entry to the webhook");
+                return;
+            }
+            Err(err) => warn!("Webhook request failed (attempt {attempt}/{MAX_ATTEMPTS}): {err:#}"),
+        }
+
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(2u64.pow(attempt - 1))).await;
+        }
+    }
+}
+
+async fn send(url: &str, body: &[u8], signature: Option<&str>) -> anyhow::Result<()> {
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client: Client<_, Body> = Client::builder().build(connector);
+
+    let mut req = Request::post(url).header("Content-Type", "application/json");
+    if let Some(signature) = signature {
+        req = req.header(SIGNATURE_HEADER, signature);
+    }
+    let req = req
+        .body(Body::from(body.to_vec()))
+        .context("Invalid webhook request")?;
+
+    let res = tokio::time::timeout(REQUEST_TIMEOUT, client.request(req))
+        .await
+        .context("Timed out")??;
+
+    if !res.status().is_success() {
+        return Err(anyhow!("Unexpected status {}", res.status()));
+    }
+
+    Ok(())
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    // HMAC accepts keys of any length, so this can't fail
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("sha256={hex}")
+}